@@ -1,40 +1,85 @@
+// Polynomial names follow the notation of the proof of solvency write-up
+#![allow(non_snake_case)]
+
 use std::time::Instant;
 
-use ark_bn254::{Bn254, Fr as F, G1Projective as G1, G2Projective as G2};
-use ark_poly::{polynomial::Polynomial, EvaluationDomain};
-use ark_poly::{univariate::DensePolynomial, Evaluations};
-use ark_std::Zero;
+use ark_bls12_381::Bls12_381;
+use ark_bn254::Bn254;
+use ark_ec::pairing::Pairing;
+use ark_poly::polynomial::Polynomial;
+use ark_std::rand::RngCore;
 use ark_std::{test_rng, UniformRand};
+use kzg_solvency::constraints::ConstraintSystem;
+use kzg_solvency::error::SolvencyError;
 use kzg_solvency::misc::{generate_random_balances, generate_users, greet};
+use kzg_solvency::prover::{generate_witness, total_balance, WitnessLayout};
+use kzg_solvency::ptau::load_ptau;
 use kzg_solvency::utils::build_zero_polynomial;
-use kzg_solvency::utils::{compute_evaluations_for_specific_omegas, get_omega_domain};
-use kzg_solvency::{kzg::KZG, lagrange::lagrange_interpolate};
+use kzg_solvency::utils::{get_omega_domain, interpolate_points};
+use kzg_solvency::kzg::{Evaluation, KzgProver, KzgVerifier, KZG};
+use kzg_solvency::lagrange::lagrange_interpolate;
+use kzg_solvency::transcript::Transcript;
 
-fn main() {
+fn main() -> Result<(), SolvencyError> {
     greet();
 
+    // The SRS is read from a Powers-of-Tau transcript over BN254 when its path is given as first
+    // argument. Otherwise tau is sampled locally, over BLS12-381 if `--bls12-381` is given. Sampling
+    // tau is only fine for testing: whoever knows tau can forge openings.
+    match std::env::args().nth(1).as_deref() {
+        Some("--bls12-381") => run::<Bls12_381, _>(insecure_setup),
+        Some(path) => {
+            let path = path.to_string();
+            run::<Bn254, _>(move |degree, mut rng| {
+                println!("  (Loading SRS from {})", path);
+                let kzg = load_ptau(path, degree)?;
+                // anyone can check that the published SRS is well formed before trusting proofs made with it
+                kzg.verify_srs(&mut rng)?;
+                Ok(kzg)
+            })
+        }
+        None => run::<Bn254, _>(insecure_setup),
+    }
+}
+
+fn insecure_setup<E: Pairing>(
+    degree: usize,
+    mut rng: &mut dyn RngCore,
+) -> Result<KZG<E>, SolvencyError> {
+    println!("  (No ptau file given, sampling tau locally)");
+    // Sampling random generators g1 and g2
+    let g1 = E::G1::rand(rng);
+    let g2 = E::G2::rand(rng);
+    Ok(KZG::<E>::insecure_setup_for_testing(g1, g2, degree, &mut rng))
+}
+
+// Runs the whole proof of solvency over the pairing E, with the SRS built by `setup` for the
+// given degree
+fn run<E: Pairing, S>(setup: S) -> Result<(), SolvencyError>
+where
+    S: FnOnce(usize, &mut dyn RngCore) -> Result<KZG<E>, SolvencyError>,
+{
     // 1. Setup
     let mut rng = test_rng();
     let n = 100;
     println!("1. Starting setup with {} random balances and users", n);
     let balances = generate_random_balances(&mut rng, n);
     let users = generate_users(&mut rng, &balances);
-
-    // Sampling a random tau and random generators g1 and g2
-    let tau = F::rand(&mut rng);
-    let g1 = G1::rand(&mut rng);
-    let g2 = G2::rand(&mut rng);
+    // The exchange declares the total of its liabilities, which the proofs show to be exact
+    let declared_total = total_balance(&users);
+    // Where the cells of each user sit in I(X), here the 14 bits of the write-up in 16 rows
+    let layout = WitnessLayout::default();
 
     // 2. Generate witness tables
     // The logic of the witness table is based on section "Improving privacy and robustness with ZK-SNARKs" of https://vitalik.ca/general/2022/11/19/proof_of_solvency.html
-    // Instantiating the witness tables over BN254. Should be working with other pairing groups.
+    // The witness tables are instantiated over the pairing E, see main for the curves that are run.
     println!("2. Generating witness tables");
-    let (p_witness, i_witness) = kzg_solvency::prover::generate_witness::<Bn254>(users).unwrap();
+    let (p_witness, i_witness) = generate_witness::<E>(users, declared_total, &layout)?;
 
     // 3. Interpolate witness tables into polynomials. i.e. computing P(X) and I(X)
     println!("3. Computing lagrange interpolation for P(X) and I(X) from witness tables");
-    let p_poly = lagrange_interpolate(&p_witness);
-    let i_poly = lagrange_interpolate(&i_witness);
+    let p_poly = lagrange_interpolate::<E>(&p_witness)?;
+    let i_poly = lagrange_interpolate::<E>(&i_witness)?;
     let poly_degree = p_poly.degree();
     let i_degree = i_poly.degree();
 
@@ -42,141 +87,171 @@ fn main() {
 
     // 4. Initiating KZG and committing to polynomials P(X) and I(X)
     println!("4. KZG-committing to P(X) and I(X)");
-    let mut kzg_bn254 = KZG::<Bn254>::new(g1, g2, poly_degree);
-    kzg_bn254.setup(tau); // setup modifies in place the struct crs
-    let p_commitment = kzg_bn254.commit(&p_poly);
-    let i_commitment = kzg_bn254.commit(&i_poly);
+    // The SRS must also commit to the quotient of the constraint system, which has about twice the
+    // degree of I(X)
+    let constraint_system = ConstraintSystem::<E::ScalarField>::new(layout, n, declared_total)?;
+    let kzg = setup(constraint_system.quotient_degree(), &mut rng)?;
+    // The witness tables are committed to in evaluation form, without going through P(X) and I(X)
+    let lagrange_key = kzg.lagrange_key(p_witness.len())?;
+    let p_commitment = lagrange_key.commit_evaluations(&p_witness)?;
+    let i_commitment = lagrange_key.commit_evaluations(&i_witness)?;
+    assert_eq!(p_commitment, kzg.commit(&p_poly)?);
+
+    // Users check that P(X) and I(X) have degree less than the domain size, so that they are
+    // determined by their evaluations over the domain. The proofs shift the polynomials up to the
+    // largest power of tau of the ceremony, and are checked against the same power in G2, which
+    // ptau files lack.
+    let bound = lagrange_key.domain_size() - 1;
+    if kzg.published_degree == kzg.degree {
+        let degree_bound_key = kzg.degree_bound_key(bound)?;
+        let degree_proofs = [
+            kzg.prove_degree_bound(&p_poly, bound)?,
+            kzg.prove_degree_bound(&i_poly, bound)?,
+        ];
+        if !degree_bound_key.verify_all(&[p_commitment, i_commitment], &degree_proofs) {
+            return Err(SolvencyError::VerificationFailed("degree bound".to_string()));
+        }
+    } else {
+        println!(
+            "  (Skipping degree bound proofs, they need all the {} powers of tau of the ceremony in G1 and G2, which ptau files do not hold)",
+            kzg.published_degree + 1
+        );
+    }
+
+    // Users only get the verifier key, which is enough to check multi-openings over the two rows
+    // of a user. Its size does not depend on the number of users.
+    let (_, verifier_key) = kzg.trim(poly_degree, 2)?;
 
     // 5. Generate opening proof for polynomial p at index `index_opened` - Constraint 1
     let index_opened = 2;
     println!(
-        "5. -- Constraint 1 -- Starting example multi-opening proof generation for user at index {}",
+        "5. -- Constraint 1 -- Starting multi-opening proof generation for all users, user at index {} will check theirs",
         index_opened
     );
     let start = Instant::now();
-    let k = n * 16; // This is n*16 because we have that P(X) has the same number of evaluations as I(X) (16 coeffs per user)
-    let (omegas, omega_elements) = get_omega_domain::<Bn254>(k);
-    let l_evaluations = compute_evaluations_for_specific_omegas::<Bn254>(
-        vec![index_opened, index_opened + 1],
-        &omega_elements,
-        &p_poly,
-    );
-    let L = Evaluations::<F>::from_vec_and_domain(l_evaluations.clone(), omegas).interpolate();
-    let Z = build_zero_polynomial::<Bn254>(&vec![
+    let k = n * layout.row_width; // P(X) has the same number of evaluations as I(X) (a row of cells per user)
+    let (_, omega_elements) = get_omega_domain::<E>(k)?;
+    // L(X) is the lowest degree polynomial matching P(X) at the opened indexes, so that users can commit to it with the small verifier key
+    let opened_omegas = [
         omega_elements[index_opened],
         omega_elements[index_opened + 1],
-    ]);
-    let pi = kzg_bn254.multi_open(
-        &p_poly,
-        &L,
-        vec![
-            omega_elements[index_opened],
-            omega_elements[index_opened + 1],
+    ];
+    let L = interpolate_points::<E>(
+        &opened_omegas,
+        &[
+            p_poly.evaluate(&opened_omegas[0]),
+            p_poly.evaluate(&opened_omegas[1]),
         ],
-    );
+    )?;
+    let Z = build_zero_polynomial::<E>(&[
+        omega_elements[index_opened],
+        omega_elements[index_opened + 1],
+    ])?;
+    // The proofs of all users are computed at once, user i owning rows (2i, 2i + 1) of P(X)
+    let user_proofs = kzg.open_all_users(&p_poly, k, n)?;
+    let pi = user_proofs[index_opened / 2];
     let duration = start.elapsed();
     println!(
-        "  (Proved inclusion of (username, balance) at indexes ({}, {}) in {:.2}s))",
-        index_opened,
-        index_opened + 1,
+        "  (Proved inclusion of (username, balance) for all {} users in {:.2}s))",
+        n,
         duration.as_secs_f64()
     );
 
     // 6. User verifies that the opening proof includes their username and balance
-    let verify = kzg_bn254.verify_multi_open(p_commitment, pi, &Z, &L);
-    assert!(verify);
+    let verify = verifier_key.verify_multi_open(p_commitment, pi, &Z, &L);
+    if !verify {
+        return Err(SolvencyError::VerificationFailed("constraint 1".to_string()));
+    }
     println!(
         "6. Multi opening proof for Constraint 1 verified to {}!",
         verify
     );
 
-    // 7. Generate opening proof for constraint 2: I(ω^(16*x)) = 0. We need to enforce that I(X) vanishes for [ω^0, ω^16, ..., ω^112]
-    println!("7. -- Constraint 2 -- Starting opening proof for I(ω^(16*x)) = 0 ");
+    // 7. Generate opening proof for constraint 3: I(ω^(w*x + b)) - P(ω^(2*x + 1)) = 0, with w the
+    // row width of the layout and b the offset of the balance cell in a user's rows.
+    // The strategy is to open I(X) at ω^b and P(X) at ω^1 and match the evaluations. Then, do the same for ω^(w + b) and ω^3, etc.
+    let constraint_3 = format!(
+        "I(ω^({}*x + {})) - P(ω^(2*x + 1)) = 0",
+        layout.row_width, layout.balance_offset
+    );
+    println!(
+        "7. -- Constraint 3 -- Starting opening proof for {}",
+        constraint_3
+    );
     let start = Instant::now();
-    let mut vanishing_omegas: Vec<F> = vec![];
+    // I(X) is opened at all the ω^(w*x + b) and P(X) at all the ω^(2*x + 1) with a single proof
+    let i_points: Vec<E::ScalarField> =
+        (0..n).map(|i| omega_elements[layout.balance_row(i)]).collect();
+    let p_points: Vec<E::ScalarField> = (0..n).map(|i| omega_elements[2 * i + 1]).collect();
+    let point_sets = vec![i_points, p_points];
+    // The challenges are derived from a transcript absorbing the commitments, so that anyone can
+    // replay it to check the proof
+    let commitments = [i_commitment, p_commitment];
+    let mut transcript = Transcript::new(b"kzg-solvency");
+    let proof =
+        kzg.multi_point_open(&[&i_poly, &p_poly], &commitments, &point_sets, &mut transcript)?;
 
-    for i in 0..n {
-        vanishing_omegas.push(omegas.element(16 * i));
-    }
-
-    let mut omega_elements: Vec<F> = vec![];
-    for element in omegas.elements() {
-        omega_elements.push(element);
-    }
-
-    // The evaluation of I(X) at the vanishing_omegas should be zero
-    let mut l_evaluations = vec![];
-    for (_, _) in omega_elements.iter().enumerate() {
-        l_evaluations.push(F::zero());
-    }
-
-    // The expected opening value for constraint 1 is the evaluation of I(X) at the vanishing_omegas, which should be zero
-    let L: DensePolynomial<F> =
-        Evaluations::<F>::from_vec_and_domain(l_evaluations.clone(), omegas).interpolate();
-
-    // Generate opening proof for constraint 1
-    let opening_proof_constraint_1 = kzg_bn254.multi_open(&i_poly, &L, vanishing_omegas.clone());
     let duration = start.elapsed();
     println!(
-        "  (Proved I(ω^(16*x)) = 0 constraint in {:.2}s))",
+        "  (Proved {} constraint in {:.2}s))",
+        constraint_3,
         duration.as_secs_f64()
     );
 
-    // Build vanishing polynomial Z(X) in [(P(x) - Q(X)) / Z(X)]
-    let Z = build_zero_polynomial::<Bn254>(&vanishing_omegas);
-
-    // 8. User verifies opening proof for constraint 1 - expect evaluation L(X) = 0
-    let verify = kzg_bn254.verify_multi_open(i_commitment, opening_proof_constraint_1, &Z, &L);
-    println!(
-        "8. Multi opening proof for Constraint 2 verified to {}!",
-        verify
+    // Both polynomials are checked against the same encrypted balances, so that they match
+    let encrypted_balances: Vec<Evaluation<E>> = balances
+        .iter()
+        .map(|balance| Evaluation::Encrypted(verifier_key.g1 * E::ScalarField::from(*balance)))
+        .collect();
+    let mut transcript = Transcript::new(b"kzg-solvency");
+    let verify = verifier_key.verify_multi_point_open(
+        &commitments,
+        &point_sets,
+        &[encrypted_balances.clone(), encrypted_balances],
+        &mut transcript,
+        &proof,
     );
+    if !verify {
+        return Err(SolvencyError::VerificationFailed("constraint 3".to_string()));
+    }
 
-    assert!(verify);
+    // 8. User should verify that the two opening proofs are true for all users and that the encrypted evaluation is the same for both proofs, 
+    println!("8. Multi opening proof for Constraint 3 verified to true");
 
-    // 9. Generate opening proof for constraint 3: I(ω^(16*x + 14) - P(ω^(2*x + 1) = 0.
-    // The strategy is to open I(X) at ω^14 and P(X) at ω^1 and match the evaluations. Then, do the same for ω^30 and ω^3, etc.
+    // 9. Prove that I(X) satisfies the gates of the write-up: each balance is made of the bits of
+    // the layout, and the running totals add up to the declared total. Its first cell gate covers
+    // constraint 2, the first cell of each user's decomposition being zero.
     println!(
-        "9. -- Constraint 3 -- Starting opening proof for I(ω^(16*x + 14) - P(ω^(2*x + 1) = 0 "
+        "9. -- Range and total constraints -- Starting proof of the gates of I(X) for a declared total of {}",
+        declared_total
     );
     let start = Instant::now();
-    // iterate over each user
-    for i in 0..n {
-        // Let's start by I(X)
-        let opening = kzg_bn254.open(&i_poly, omega_elements[16 * i + 14], F::from(balances[i]));
-
-        let encrypted_evaluation = kzg_bn254.g1 * F::from(balances[i]);
-
-        let verify = kzg_bn254.verify_from_encrypted_y(
-            encrypted_evaluation,
-            omega_elements[16 * i + 14],
-            i_commitment,
-            opening,
-        );
-
-        assert!(verify);
-
-        // Now let's do the same for P(X)
-        let opening = kzg_bn254.open(&p_poly, omega_elements[2 * i + 1], F::from(balances[i]));
-        let verify = kzg_bn254.verify_from_encrypted_y(
-            encrypted_evaluation,
-            omega_elements[2 * i + 1],
-            p_commitment,
-            opening,
-        );
-
-        assert!(verify);
-    }
-
+    let (prover_key, _) = kzg.trim(constraint_system.quotient_degree(), 1)?;
+    let mut transcript = Transcript::new(b"kzg-solvency-constraints");
+    let constraint_proof =
+        constraint_system.prove::<KZG<E>>(&prover_key, &i_witness, &mut transcript)?;
     let duration = start.elapsed();
     println!(
-        "  (Proved I(ω^(16*x + 14) - P(ω^(2*x + 1) = 0 constraint in {:.2}s))",
+        "  (Proved the range and total constraints in {:.2}s))",
         duration.as_secs_f64()
     );
 
-    // 10. User should verify that the two opening proofs are true for all users and that the encrypted evaluation is the same for both proofs, 
-    println!("10. Multi opening proof for Constraint 3 verified to true");
+    // 10. User replays the transcript and checks the gates at the challenge point
+    let mut transcript = Transcript::new(b"kzg-solvency-constraints");
+    let verify = constraint_system.verify::<KZG<E>>(
+        &verifier_key,
+        &i_commitment,
+        &constraint_proof,
+        &mut transcript,
+    );
+    if !verify {
+        return Err(SolvencyError::VerificationFailed(
+            "range and total constraints".to_string(),
+        ));
+    }
+    println!("10. Range and total constraints verified to true");
 
     // TO KEEP: Used for terminal pretty printing
-    println!("");
+    println!();
+    Ok(())
 }
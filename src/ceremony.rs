@@ -0,0 +1,325 @@
+use std::path::Path;
+
+use ark_ec::pairing::Pairing;
+use ark_ec::{AffineRepr, CurveGroup};
+use ark_ff::Field;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::rand::Rng;
+use ark_std::{UniformRand, Zero};
+use zeroize::Zeroizing;
+
+use crate::error::SolvencyError;
+use crate::kzg::KZG;
+use crate::serialization::{read_from_file, write_to_file, CurveId, ObjectKind};
+use crate::transcript::Transcript;
+
+// Multi-party powers of tau ceremony. Each participant takes the current powers [tau^i]_1 and
+// [tau^i]_2, multiplies the i-th ones by s^i for a secret s of their own and publishes the new
+// powers along with a contribution proof. The final tau is the product of all the secrets, so it
+// stays unknown as long as a single participant threw their secret away.
+// See https://eprint.iacr.org/2022/1592 for the update proofs.
+
+const POK_DOMAIN: &[u8] = b"kzg-solvency-ceremony-pok";
+
+/// Powers of tau, as they are handed from one participant to the next
+#[derive(Debug, Clone, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct Powers<E: Pairing> {
+    pub g1_powers: Vec<E::G1Affine>,
+    pub g2_powers: Vec<E::G2Affine>,
+}
+
+/// Schnorr proof of knowledge of s for [s]_1
+#[derive(Debug, Clone, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct KnowledgeProof<E: Pairing> {
+    pub r: E::G1Affine,
+    pub response: E::ScalarField,
+}
+
+/// What a participant publishes next to their new powers
+#[derive(Debug, Clone, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct Contribution<E: Pairing> {
+    // [tau * s]_1, the second power after the update
+    pub tau_g1: E::G1Affine,
+    pub s_g1: E::G1Affine,
+    pub s_g2: E::G2Affine,
+    pub pok: KnowledgeProof<E>,
+}
+
+impl<E: Pairing> Powers<E> {
+    /// Starting point of a ceremony: tau = 1, i.e. every power is the generator
+    pub fn new(g1: E::G1, g2: E::G2, degree: usize) -> Self {
+        Self {
+            g1_powers: vec![g1.into_affine(); degree + 1],
+            g2_powers: vec![g2.into_affine(); degree + 1],
+        }
+    }
+
+    pub fn degree(&self) -> usize {
+        self.g1_powers.len() - 1
+    }
+
+    /// Turns the final powers of a ceremony into the SRS used by the prover
+    pub fn into_kzg(self) -> Result<KZG<E>, SolvencyError> {
+        KZG::from_powers(self.g1_powers, self.g2_powers)
+    }
+
+}
+
+impl<E: CurveId> Powers<E> {
+    pub fn write_to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), SolvencyError> {
+        write_to_file::<E, _, _>(ObjectKind::CeremonyPowers, self, path)
+    }
+
+    pub fn read_from_file<P: AsRef<Path>>(path: P) -> Result<Self, SolvencyError> {
+        read_from_file::<E, _, _>(ObjectKind::CeremonyPowers, path)
+    }
+}
+
+impl<E: CurveId> Contribution<E> {
+    pub fn write_to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), SolvencyError> {
+        write_to_file::<E, _, _>(ObjectKind::CeremonyContribution, self, path)
+    }
+
+    pub fn read_from_file<P: AsRef<Path>>(path: P) -> Result<Self, SolvencyError> {
+        read_from_file::<E, _, _>(ObjectKind::CeremonyContribution, path)
+    }
+}
+
+/// Re-randomizes `powers` with a fresh secret and returns the new powers with the proof of the update
+pub fn contribute<E: Pairing, R: Rng>(
+    powers: &Powers<E>,
+    rng: &mut R,
+) -> Result<(Powers<E>, Contribution<E>), SolvencyError> {
+    if powers.g1_powers.len() < 2 || powers.g2_powers.len() < 2 {
+        return Err(SolvencyError::InvalidSrs(
+            "a ceremony needs at least 2 powers in G1 and G2".to_string(),
+        ));
+    }
+    // the secret, its powers and the Schnorr nonce are wiped from memory when dropped
+    let mut s = Zeroizing::new(E::ScalarField::rand(rng));
+    while s.is_zero() {
+        s = Zeroizing::new(E::ScalarField::rand(rng));
+    }
+
+    let mut g1_powers = vec![];
+    let mut s_i = Zeroizing::new(E::ScalarField::ONE);
+    for point in &powers.g1_powers {
+        g1_powers.push(*point * *s_i);
+        *s_i *= *s;
+    }
+    let mut g2_powers = vec![];
+    let mut s_i = Zeroizing::new(E::ScalarField::ONE);
+    for point in &powers.g2_powers {
+        g2_powers.push(*point * *s_i);
+        *s_i *= *s;
+    }
+    let updated = Powers {
+        g1_powers: E::G1::normalize_batch(&g1_powers),
+        g2_powers: E::G2::normalize_batch(&g2_powers),
+    };
+
+    let g1 = powers.g1_powers[0];
+    let s_g1 = (g1 * *s).into_affine();
+    let s_g2 = (powers.g2_powers[0] * *s).into_affine();
+
+    // Schnorr proof bound to the powers the participant built upon
+    let k = Zeroizing::new(E::ScalarField::rand(rng));
+    let r = (g1 * *k).into_affine();
+    let c = pok_challenge::<E>(&powers.g1_powers[1], &s_g1, &r);
+    let pok = KnowledgeProof {
+        r,
+        response: *k + c * *s,
+    };
+
+    let contribution = Contribution {
+        tau_g1: updated.g1_powers[1],
+        s_g1,
+        s_g2,
+        pok,
+    };
+    Ok((updated, contribution))
+}
+
+/// Checks that `after` is `before` updated by the secret `contribution` proves knowledge of,
+/// and that `after` is a well formed SRS
+pub fn verify_contribution<E: Pairing, R: Rng>(
+    before: &Powers<E>,
+    after: &Powers<E>,
+    contribution: &Contribution<E>,
+    rng: &mut R,
+) -> Result<(), SolvencyError> {
+    if before.g1_powers.len() < 2 || before.g2_powers.len() < 2 {
+        return Err(SolvencyError::InvalidSrs(
+            "a ceremony needs at least 2 powers in G1 and G2".to_string(),
+        ));
+    }
+    if before.g1_powers.len() != after.g1_powers.len()
+        || before.g2_powers.len() != after.g2_powers.len()
+    {
+        return Err(SolvencyError::VerificationFailed(
+            "the contribution changed the number of powers".to_string(),
+        ));
+    }
+    if after.g1_powers[1] != contribution.tau_g1 {
+        return Err(SolvencyError::VerificationFailed(
+            "the contribution does not match the new powers".to_string(),
+        ));
+    }
+    verify_update_step(
+        before.g1_powers[0],
+        before.g2_powers[0],
+        before.g1_powers[1],
+        contribution,
+    )?;
+    // generators are left untouched by an update
+    if after.g1_powers[0] != before.g1_powers[0] || after.g2_powers[0] != before.g2_powers[0] {
+        return Err(SolvencyError::VerificationFailed(
+            "the contribution changed the generators".to_string(),
+        ));
+    }
+    KZG::<E>::from_powers(after.g1_powers.clone(), after.g2_powers.clone())?.verify_srs(rng)
+}
+
+/// Checks a whole ceremony: each contribution builds on the previous one, starting from `initial`,
+/// and `last` holds the powers published by the last participant
+pub fn verify_transcript<E: Pairing, R: Rng>(
+    initial: &Powers<E>,
+    contributions: &[Contribution<E>],
+    last: &Powers<E>,
+    rng: &mut R,
+) -> Result<(), SolvencyError> {
+    if initial.g1_powers.len() < 2 || initial.g2_powers.len() < 2 {
+        return Err(SolvencyError::InvalidSrs(
+            "a ceremony needs at least 2 powers in G1 and G2".to_string(),
+        ));
+    }
+    let g1 = initial.g1_powers[0];
+    let g2 = initial.g2_powers[0];
+    // the ceremony starts from tau = 1
+    if initial.g1_powers.iter().any(|point| *point != g1)
+        || initial.g2_powers.iter().any(|point| *point != g2)
+    {
+        return Err(SolvencyError::VerificationFailed(
+            "the initial powers are not all equal to the generators".to_string(),
+        ));
+    }
+
+    let mut tau_g1 = initial.g1_powers[1];
+    for (i, contribution) in contributions.iter().enumerate() {
+        verify_update_step(g1, g2, tau_g1, contribution).map_err(|_| {
+            SolvencyError::VerificationFailed(format!("contribution {} is invalid", i))
+        })?;
+        tau_g1 = contribution.tau_g1;
+    }
+
+    if last.g1_powers.len() != initial.g1_powers.len()
+        || last.g2_powers.len() != initial.g2_powers.len()
+        || last.g1_powers[0] != g1
+        || last.g2_powers[0] != g2
+        || last.g1_powers[1] != tau_g1
+    {
+        return Err(SolvencyError::VerificationFailed(
+            "the final powers do not match the last contribution".to_string(),
+        ));
+    }
+    KZG::<E>::from_powers(last.g1_powers.clone(), last.g2_powers.clone())?.verify_srs(rng)
+}
+
+/// Reads the powers at `input`, contributes to them and writes the new powers and the
+/// contribution proof to `output` and `contribution_output`
+pub fn contribute_file<E: CurveId, R: Rng, P: AsRef<Path>>(
+    input: P,
+    output: P,
+    contribution_output: P,
+    rng: &mut R,
+) -> Result<Contribution<E>, SolvencyError> {
+    let powers = Powers::<E>::read_from_file(input)?;
+    let (updated, contribution) = contribute(&powers, rng)?;
+    updated.write_to_file(output)?;
+    contribution.write_to_file(contribution_output)?;
+    Ok(contribution)
+}
+
+pub fn verify_contribution_file<E: CurveId, R: Rng, P: AsRef<Path>>(
+    before: P,
+    after: P,
+    contribution: P,
+    rng: &mut R,
+) -> Result<(), SolvencyError> {
+    verify_contribution(
+        &Powers::<E>::read_from_file(before)?,
+        &Powers::<E>::read_from_file(after)?,
+        &Contribution::<E>::read_from_file(contribution)?,
+        rng,
+    )
+}
+
+pub fn verify_transcript_files<E: CurveId, R: Rng, P: AsRef<Path>>(
+    initial: P,
+    contributions: &[P],
+    last: P,
+    rng: &mut R,
+) -> Result<(), SolvencyError> {
+    let contributions = contributions
+        .iter()
+        .map(Contribution::<E>::read_from_file)
+        .collect::<Result<Vec<_>, _>>()?;
+    verify_transcript(
+        &Powers::<E>::read_from_file(initial)?,
+        &contributions,
+        &Powers::<E>::read_from_file(last)?,
+        rng,
+    )
+}
+
+// Checks one link of the chain: the contribution moved [tau]_1 from `tau_g1_before` to
+// `contribution.tau_g1` with a secret s it knows
+fn verify_update_step<E: Pairing>(
+    g1: E::G1Affine,
+    g2: E::G2Affine,
+    tau_g1_before: E::G1Affine,
+    contribution: &Contribution<E>,
+) -> Result<(), SolvencyError> {
+    // s = 0 would erase all previous contributions
+    if contribution.s_g1.is_zero() {
+        return Err(SolvencyError::VerificationFailed(
+            "the contribution secret is zero".to_string(),
+        ));
+    }
+
+    // response * g1 == r + c * [s]_1
+    let c = pok_challenge::<E>(&tau_g1_before, &contribution.s_g1, &contribution.pok.r);
+    if g1 * contribution.pok.response != contribution.pok.r + contribution.s_g1 * c {
+        return Err(SolvencyError::VerificationFailed(
+            "invalid proof of knowledge of the contribution secret".to_string(),
+        ));
+    }
+
+    // [s]_1 and [s]_2 hide the same s
+    if E::pairing(contribution.s_g1, g2) != E::pairing(g1, contribution.s_g2) {
+        return Err(SolvencyError::VerificationFailed(
+            "[s]_1 and [s]_2 do not match".to_string(),
+        ));
+    }
+
+    // e([tau * s]_1, g2) == e([tau]_1, [s]_2)
+    if E::pairing(contribution.tau_g1, g2) != E::pairing(tau_g1_before, contribution.s_g2) {
+        return Err(SolvencyError::VerificationFailed(
+            "the new powers are not the previous ones updated by s".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+// Fiat-Shamir challenge of the Schnorr proof
+fn pok_challenge<E: Pairing>(
+    tau_g1_before: &E::G1Affine,
+    s_g1: &E::G1Affine,
+    r: &E::G1Affine,
+) -> E::ScalarField {
+    let mut transcript = Transcript::new(POK_DOMAIN);
+    transcript.append(b"tau_g1_before", tau_g1_before);
+    transcript.append(b"s_g1", s_g1);
+    transcript.append(b"r", r);
+    transcript.challenge_scalar(b"c")
+}
@@ -0,0 +1,355 @@
+use std::fmt;
+
+use ark_ff::{batch_inversion, Field, PrimeField};
+use ark_poly::univariate::DensePolynomial;
+use ark_poly::{DenseUVPolynomial, EvaluationDomain, GeneralEvaluationDomain, Polynomial};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+
+use crate::error::SolvencyError;
+use crate::pcs::PolynomialCommitmentScheme;
+use crate::prover::{average_balance, WitnessLayout};
+use crate::transcript::Transcript;
+
+// Names of the gates, in the order they are combined with the powers of alpha
+const GATES: [&str; 4] = ["first cell", "bits", "running total", "final total"];
+
+/// Gates that the liabilities polynomial I(X) satisfies on every row of its domain, from the
+/// proof of solvency write-up (https://vitalik.ca/general/2022/11/19/proof_of_solvency.html).
+/// Each user owns a block of rows laid out by a [`WitnessLayout`]. With ω the generator of the
+/// domain, w the row width, and selectors that are one on the rows listed and zero elsewhere:
+/// - first cell, on the first cell of the decomposition in each block: I(X) = 0
+/// - bits, on the rows before the balance: (I(ωX) - 2I(X)) * (I(ωX) - 2I(X) - 1) = 0, so that
+///   the balance is a sum of `bits` bits
+/// - running total, on the running total rows, with d the offset from the running total to the
+///   balance: I(X) = I(ω^-w X) + I(ω^d X) - average, the previous total being zero for the first
+///   user and the average being the declared total over the number of users
+/// - final total, on the running total row of the last user: I(X) = 0
+///
+/// Rows past the last user are padding and are not constrained.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConstraintSystem<F: PrimeField> {
+    domain: GeneralEvaluationDomain<F>,
+    layout: WitnessLayout,
+    n_users: usize,
+    declared_total: u128,
+    average: F,
+}
+
+/// Proof that the committed I(X) satisfies the gates: the commitment to the quotient of the
+/// combined gates by the vanishing polynomial, the evaluations of I(X) at ζ, ωζ, ω^d ζ and
+/// ω^-w ζ and of the quotient at ζ, and their batched opening
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+pub struct ConstraintProof<PCS: PolynomialCommitmentScheme> {
+    pub quotient: PCS::Commitment,
+    pub evaluations: Vec<PCS::Field>,
+    pub opening: PCS::BatchProof,
+}
+
+// Derives would require the scheme itself to be Debug, Clone and PartialEq, not only its types
+impl<PCS: PolynomialCommitmentScheme> fmt::Debug for ConstraintProof<PCS> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConstraintProof")
+            .field("quotient", &self.quotient)
+            .field("evaluations", &self.evaluations)
+            .field("opening", &self.opening)
+            .finish()
+    }
+}
+
+impl<PCS: PolynomialCommitmentScheme> Clone for ConstraintProof<PCS> {
+    fn clone(&self) -> Self {
+        Self {
+            quotient: self.quotient.clone(),
+            evaluations: self.evaluations.clone(),
+            opening: self.opening.clone(),
+        }
+    }
+}
+
+impl<PCS: PolynomialCommitmentScheme> PartialEq for ConstraintProof<PCS> {
+    fn eq(&self, other: &Self) -> bool {
+        self.quotient == other.quotient
+            && self.evaluations == other.evaluations
+            && self.opening == other.opening
+    }
+}
+
+impl<F: PrimeField> ConstraintSystem<F> {
+    /// Gates for `n_users` blocks of rows laid out by `layout`, whose balances sum up to
+    /// `declared_total`. The average is taken in the field, so that the final total is zero even
+    /// when `n_users` does not divide the declared total.
+    pub fn new(
+        layout: WitnessLayout,
+        n_users: usize,
+        declared_total: u128,
+    ) -> Result<Self, SolvencyError> {
+        layout.validate()?;
+        if n_users == 0 {
+            return Err(SolvencyError::EmptyUsers);
+        }
+        let size = layout.row_width * n_users;
+        let domain =
+            GeneralEvaluationDomain::new(size).ok_or(SolvencyError::InvalidDomainSize(size))?;
+        Ok(Self {
+            domain,
+            layout,
+            n_users,
+            declared_total,
+            average: average_balance(declared_total, n_users)?,
+        })
+    }
+
+    pub fn domain_size(&self) -> usize {
+        self.domain.size()
+    }
+
+    /// Degree of the quotient, which the prover key must be able to commit to
+    pub fn quotient_degree(&self) -> usize {
+        2 * self.domain.size() - 3
+    }
+
+    /// Checks the gates on every row of the witness of I(X), padded with zeroes to the domain
+    pub fn check_witness(&self, witness: &[F]) -> Result<(), SolvencyError> {
+        let witness = self.padded(witness)?;
+        let selectors = self.selectors();
+        let n = witness.len();
+        for row in 0..n {
+            let row_selectors = selectors.each_ref().map(|selector| selector[row]);
+            let values = self.rotations().map(|rotation| witness[rotated(row, rotation, n)]);
+            if let Some(gate) = self.gates(row_selectors, values).iter().position(|g| !g.is_zero())
+            {
+                return Err(SolvencyError::UnsatisfiedConstraint {
+                    gate: GATES[gate],
+                    row,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Proves that the witness of I(X) satisfies the gates. The gates are combined with powers of
+    /// a challenge alpha and divided by the vanishing polynomial of the domain, on a coset of a
+    /// domain four times larger since the combined gates have degree about 3n. The commitment to
+    /// I(X) and the public inputs are appended to the `transcript` before any challenge.
+    pub fn prove<PCS: PolynomialCommitmentScheme<Field = F>>(
+        &self,
+        prover_key: &PCS::ProverKey,
+        witness: &[F],
+        transcript: &mut Transcript,
+    ) -> Result<ConstraintProof<PCS>, SolvencyError> {
+        self.check_witness(witness)?;
+        let witness = self.padded(witness)?;
+        let i_polynomial = DensePolynomial::from_coefficients_vec(self.domain.ifft(&witness));
+        let i_commitment = PCS::commit(prover_key, &i_polynomial)?;
+        let alpha = self.alpha(&i_commitment, transcript);
+
+        let n = self.domain.size();
+        let coset = GeneralEvaluationDomain::<F>::new(4 * n)
+            .and_then(|domain| domain.get_coset(F::GENERATOR))
+            .ok_or(SolvencyError::InvalidDomainSize(4 * n))?;
+        let selectors = self
+            .selectors()
+            .map(|selector| coset.fft(&self.domain.ifft(&selector)));
+        let rotations = self.rotations().map(|rotation| {
+            // coefficients of I(ω^k X) are c_j * ω^(kj)
+            let omega_k = self.omega_power(rotation);
+            let mut power = F::one();
+            let shifted: Vec<F> = i_polynomial
+                .coeffs
+                .iter()
+                .map(|c| {
+                    let shifted = *c * power;
+                    power *= omega_k;
+                    shifted
+                })
+                .collect();
+            coset.fft(&shifted)
+        });
+
+        let mut vanishing: Vec<F> = coset
+            .elements()
+            .map(|x| self.domain.evaluate_vanishing_polynomial(x))
+            .collect();
+        batch_inversion(&mut vanishing);
+        let quotient_evaluations: Vec<F> = (0..coset.size())
+            .map(|k| {
+                let gates = self.gates(
+                    selectors.each_ref().map(|selector| selector[k]),
+                    rotations.each_ref().map(|rotation| rotation[k]),
+                );
+                combine(alpha, &gates) * vanishing[k]
+            })
+            .collect();
+        let quotient = DensePolynomial::from_coefficients_vec(coset.ifft(&quotient_evaluations));
+
+        let quotient_commitment = PCS::commit(prover_key, &quotient)?;
+        transcript.append(b"quotient", &quotient_commitment);
+        let zeta = transcript.challenge_scalar::<F>(b"zeta");
+        let point_sets = self.point_sets(zeta);
+        let mut evaluations: Vec<F> = point_sets[0]
+            .iter()
+            .map(|x| i_polynomial.evaluate(x))
+            .collect();
+        evaluations.push(quotient.evaluate(&zeta));
+        let opening = PCS::batch_open(
+            prover_key,
+            &[&i_polynomial, &quotient],
+            &[i_commitment, quotient_commitment.clone()],
+            &point_sets,
+            transcript,
+        )?;
+        Ok(ConstraintProof {
+            quotient: quotient_commitment,
+            evaluations,
+            opening,
+        })
+    }
+
+    /// Checks the combined gates against the quotient at ζ, and the opening of the evaluations,
+    /// replaying the prover's `transcript` from `i_commitment` and the public inputs
+    pub fn verify<PCS: PolynomialCommitmentScheme<Field = F>>(
+        &self,
+        verifier_key: &PCS::VerifierKey,
+        i_commitment: &PCS::Commitment,
+        proof: &ConstraintProof<PCS>,
+        transcript: &mut Transcript,
+    ) -> bool {
+        if proof.evaluations.len() != 5 {
+            return false;
+        }
+        let alpha = self.alpha(i_commitment, transcript);
+        transcript.append(b"quotient", &proof.quotient);
+        let zeta = transcript.challenge_scalar::<F>(b"zeta");
+
+        // selectors at ζ, from the Lagrange basis of the domain
+        let lagrange = self.domain.evaluate_all_lagrange_coefficients(zeta);
+        let selectors = self.selectors().map(|selector| {
+            selector
+                .iter()
+                .zip(&lagrange)
+                .filter(|(s, _)| !s.is_zero())
+                .map(|(_, l)| *l)
+                .sum::<F>()
+        });
+        let values = [
+            proof.evaluations[0],
+            proof.evaluations[1],
+            proof.evaluations[2],
+            proof.evaluations[3],
+        ];
+        let gates = combine(alpha, &self.gates(selectors, values));
+        let vanishing = self.domain.evaluate_vanishing_polynomial(zeta);
+        if gates != proof.evaluations[4] * vanishing {
+            return false;
+        }
+
+        let commitments = [i_commitment.clone(), proof.quotient.clone()];
+        let values = vec![proof.evaluations[..4].to_vec(), proof.evaluations[4..].to_vec()];
+        PCS::batch_verify(
+            verifier_key,
+            &commitments,
+            &self.point_sets(zeta),
+            &values,
+            transcript,
+            &proof.opening,
+        )
+    }
+
+    // The commitment to I(X) and the public inputs are appended before alpha is squeezed, so that
+    // none of them can be chosen after the challenges
+    fn alpha<C: CanonicalSerialize>(&self, i_commitment: &C, transcript: &mut Transcript) -> F {
+        transcript.append(b"i_commitment", i_commitment);
+        let layout = self.layout;
+        for offset in [layout.bits, layout.row_width, layout.balance_offset, layout.total_offset] {
+            transcript.append(b"layout", &(offset as u64));
+        }
+        transcript.append(b"n_users", &(self.n_users as u64));
+        transcript.append_message(b"declared_total", &self.declared_total.to_le_bytes());
+        transcript.challenge_scalar(b"alpha")
+    }
+
+    fn padded(&self, witness: &[F]) -> Result<Vec<F>, SolvencyError> {
+        let n = self.domain.size();
+        if witness.len() > n || witness.len() < self.layout.row_width * self.n_users {
+            return Err(SolvencyError::LengthMismatch {
+                expected: n,
+                found: witness.len(),
+            });
+        }
+        let mut padded = witness.to_vec();
+        padded.resize(n, F::zero());
+        Ok(padded)
+    }
+
+    // Evaluations over the domain of the first cell, bits, running total, carried total and
+    // final total selectors. The carried total is the running total but for the first user.
+    fn selectors(&self) -> [Vec<F>; 5] {
+        let n = self.domain.size();
+        let layout = &self.layout;
+        let mut selectors = [(); 5].map(|_| vec![F::zero(); n]);
+        for user in 0..self.n_users {
+            let start = layout.row_width * user + layout.decomposition_offset();
+            selectors[0][start] = F::one();
+            selectors[1][start..layout.balance_row(user)].fill(F::one());
+            selectors[2][layout.total_row(user)] = F::one();
+            if user > 0 {
+                selectors[3][layout.total_row(user)] = F::one();
+            }
+        }
+        selectors[4][layout.total_row(self.n_users - 1)] = F::one();
+        selectors
+    }
+
+    // I(X) is read at X, ωX, at the balance of the block from its running total, and at the
+    // same row of the previous block
+    fn rotations(&self) -> [isize; 4] {
+        let layout = &self.layout;
+        [
+            0,
+            1,
+            layout.balance_offset as isize - layout.total_offset as isize,
+            -(layout.row_width as isize),
+        ]
+    }
+
+    fn omega_power(&self, rotation: isize) -> F {
+        let omega = if rotation < 0 {
+            self.domain.group_gen_inv()
+        } else {
+            self.domain.group_gen()
+        };
+        omega.pow([rotation.unsigned_abs() as u64])
+    }
+
+    fn point_sets(&self, zeta: F) -> Vec<Vec<F>> {
+        let rotated = self
+            .rotations()
+            .iter()
+            .map(|rotation| self.omega_power(*rotation) * zeta)
+            .collect();
+        vec![rotated, vec![zeta]]
+    }
+
+    // Values of the gates at a row, or at any point once the selectors and the rotations of I(X)
+    // are evaluated there
+    fn gates(&self, selectors: [F; 5], values: [F; 4]) -> [F; 4] {
+        let [first, bits, total, carry, last] = selectors;
+        let [current, next, balance, previous_user] = values;
+        let bit = next - current.double();
+        [
+            first * current,
+            bits * bit * (bit - F::one()),
+            total * (current - balance + self.average) - carry * previous_user,
+            last * current,
+        ]
+    }
+}
+
+// sum alpha^k * gates[k]
+fn combine<F: Field>(alpha: F, gates: &[F]) -> F {
+    gates.iter().rev().fold(F::zero(), |acc, gate| acc * alpha + gate)
+}
+
+fn rotated(row: usize, rotation: isize, n: usize) -> usize {
+    (row as isize + rotation).rem_euclid(n as isize) as usize
+}
@@ -0,0 +1,95 @@
+use ark_serialize::SerializationError;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SolvencyError {
+    // No evaluation domain of roots of unity exists for this size in the scalar field
+    InvalidDomainSize(usize),
+    // The polynomial (or quotient) has more coefficients than there are powers of tau in the SRS
+    DegreeTooLarge { degree: usize, max_degree: usize },
+    // The balance of the user at `user` does not fit the witness layout
+    InvalidBalance { user: usize, balance: u64 },
+    // The witness layout does not fit the balance cells in its rows, the string tells why
+    InvalidLayout(String),
+    // A witness can not be built for an empty list of users
+    EmptyUsers,
+    // A zero polynomial needs at least one root
+    EmptyRoots,
+    // Interpolation points must be pairwise distinct
+    DuplicatePoints,
+    // Inputs that go together (e.g. polynomials and their point sets) have different lengths
+    LengthMismatch { expected: usize, found: usize },
+    // A row index outside of the evaluation domain
+    IndexOutOfRange { index: usize, size: usize },
+    // The witness does not satisfy the named gate at `row`
+    UnsatisfiedConstraint { gate: &'static str, row: usize },
+    // A proof did not verify, the string tells which check failed
+    VerificationFailed(String),
+    // The SRS (or the file it is read from) is badly encoded or too small
+    InvalidSrs(String),
+    // The power at `index` of the `group` ("g1", "g2" or "vk") part of the SRS is not consistent
+    // with the other powers of tau
+    SrsMismatch { group: &'static str, index: usize },
+    // Reading or writing a file failed
+    Io(String),
+    // A value could not be (de)serialized, or its encoding is invalid
+    Serialization(String),
+}
+
+impl fmt::Display for SolvencyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SolvencyError::InvalidDomainSize(n) => {
+                write!(f, "no evaluation domain of size {} in the scalar field", n)
+            }
+            SolvencyError::DegreeTooLarge { degree, max_degree } => write!(
+                f,
+                "polynomial of degree {} exceeds the SRS maximum degree {}",
+                degree, max_degree
+            ),
+            SolvencyError::InvalidBalance { user, balance } => write!(
+                f,
+                "balance {} of user {} does not fit the witness layout",
+                balance, user
+            ),
+            SolvencyError::InvalidLayout(reason) => write!(f, "invalid witness layout: {}", reason),
+            SolvencyError::EmptyUsers => write!(f, "the list of users is empty"),
+            SolvencyError::EmptyRoots => write!(f, "the zero polynomial needs at least one root"),
+            SolvencyError::DuplicatePoints => write!(f, "interpolation points must be distinct"),
+            SolvencyError::LengthMismatch { expected, found } => {
+                write!(f, "expected {} inputs, found {}", expected, found)
+            }
+            SolvencyError::IndexOutOfRange { index, size } => {
+                write!(f, "index {} is out of a domain of size {}", index, size)
+            }
+            SolvencyError::UnsatisfiedConstraint { gate, row } => {
+                write!(f, "the witness does not satisfy the {} gate at row {}", gate, row)
+            }
+            SolvencyError::VerificationFailed(check) => {
+                write!(f, "verification failed: {}", check)
+            }
+            SolvencyError::InvalidSrs(reason) => write!(f, "invalid SRS: {}", reason),
+            SolvencyError::SrsMismatch { group, index } => write!(
+                f,
+                "SRS power {} in {} is not consistent with the other powers of tau",
+                index, group
+            ),
+            SolvencyError::Io(reason) => write!(f, "i/o error: {}", reason),
+            SolvencyError::Serialization(reason) => write!(f, "serialization error: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for SolvencyError {}
+
+impl From<std::io::Error> for SolvencyError {
+    fn from(err: std::io::Error) -> Self {
+        SolvencyError::Io(err.to_string())
+    }
+}
+
+impl From<SerializationError> for SolvencyError {
+    fn from(err: SerializationError) -> Self {
+        SolvencyError::Serialization(err.to_string())
+    }
+}
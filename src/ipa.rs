@@ -0,0 +1,358 @@
+use ark_ec::pairing::Pairing;
+use ark_ec::{AffineRepr, CurveGroup, VariableBaseMSM};
+use ark_ff::Field;
+use ark_poly::univariate::DensePolynomial;
+use ark_poly::Polynomial;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::{One, Zero};
+use sha2::{Digest, Sha256};
+
+use crate::error::SolvencyError;
+use crate::kzg::{shplonk_commitment, shplonk_open, Evaluation};
+use crate::pcs::PolynomialCommitmentScheme;
+use crate::transcript::Transcript;
+
+const GENERATORS_DOMAIN: &[u8] = b"kzg-solvency-ipa-generators";
+const INNER_PRODUCT_DOMAIN: &[u8] = b"kzg-solvency-ipa-inner-product";
+const TRANSCRIPT_DOMAIN: &[u8] = b"kzg-solvency-ipa";
+
+/// Transparent polynomial commitments with an inner product argument (Bulletproofs, as used in
+/// Halo, https://eprint.iacr.org/2019/1021) over the G1 group of `E`. The pairing is never used:
+/// the generators are hashed to the curve, so nobody knows a relation between them and there is
+/// no trusted setup. Proofs are logarithmic in the degree but verifying them is linear.
+#[derive(Debug, Clone, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct IPA<E: Pairing> {
+    // one generator per coefficient, a power of two of them
+    pub generators: Vec<E::G1Affine>,
+    // generator of the inner product, i.e. of the evaluation
+    pub u: E::G1Affine,
+}
+
+/// Opening proof: the cross terms of each halving round and the last folded coefficient
+#[derive(Debug, Clone, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct IpaProof<E: Pairing> {
+    pub l: Vec<E::G1>,
+    pub r: Vec<E::G1>,
+    pub a: E::ScalarField,
+}
+
+/// Multi-point opening, reduced to a single opening as for `KZG::multi_point_open`
+#[derive(Debug, Clone, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct IpaBatchProof<E: Pairing> {
+    pub w: E::G1,
+    pub proof: IpaProof<E>,
+}
+
+impl<E: Pairing> IPA<E> {
+    /// Generators to commit to polynomials of degree up to `degree`, rounded up so that there is
+    /// a power of two of them. Anyone can recompute them. None of them depends on the size of the
+    /// setup, so that a trimmed setup is the setup of the smaller size.
+    pub fn setup(degree: usize) -> Self {
+        let n = (degree + 1).next_power_of_two();
+        let generators = (0..n)
+            .map(|index| hash_to_curve::<E>(GENERATORS_DOMAIN, index as u64))
+            .collect();
+        let u = hash_to_curve::<E>(INNER_PRODUCT_DOMAIN, 0);
+        Self { generators, u }
+    }
+
+    pub fn max_degree(&self) -> usize {
+        self.generators.len() - 1
+    }
+
+    /// Generators for polynomials of degree up to `max_degree`, which are the first ones of the
+    /// setup so that commitments do not change
+    pub fn trim(&self, max_degree: usize) -> Result<Self, SolvencyError> {
+        let n = (max_degree + 1).next_power_of_two();
+        if n > self.generators.len() {
+            return Err(SolvencyError::DegreeTooLarge {
+                degree: max_degree,
+                max_degree: self.max_degree(),
+            });
+        }
+        Ok(Self {
+            generators: self.generators[..n].to_vec(),
+            u: self.u,
+        })
+    }
+
+    pub fn commit(
+        &self,
+        polynomial: &DensePolynomial<E::ScalarField>,
+    ) -> Result<E::G1, SolvencyError> {
+        if polynomial.coeffs.len() > self.generators.len() {
+            return Err(SolvencyError::DegreeTooLarge {
+                degree: polynomial.coeffs.len() - 1,
+                max_degree: self.max_degree(),
+            });
+        }
+        Ok(E::G1::msm_unchecked(&self.generators, &polynomial.coeffs))
+    }
+
+    /// Proves that `polynomial` evaluates to `y` at `z`. Each round halves the coefficients a and
+    /// the powers of z b, folding them with a challenge x as a_lo * x + a_hi / x and
+    /// b_lo / x + b_hi * x, and sends the cross terms L and R.
+    pub fn open(
+        &self,
+        polynomial: &DensePolynomial<E::ScalarField>,
+        z: E::ScalarField,
+        y: E::ScalarField,
+    ) -> Result<IpaProof<E>, SolvencyError> {
+        let commitment = self.commit(polynomial)?;
+        let n = self.generators.len();
+        let mut a = polynomial.coeffs.clone();
+        a.resize(n, E::ScalarField::zero());
+        let mut b = powers_of::<E>(z, n);
+        let mut g: Vec<E::G1> = self.generators.iter().map(|g| g.into_group()).collect();
+
+        let mut transcript = Transcript::new(TRANSCRIPT_DOMAIN);
+        let u = self.u * bind_evaluation::<E>(&mut transcript, commitment, z, y);
+        let mut l = vec![];
+        let mut r = vec![];
+        while a.len() > 1 {
+            let half = a.len() / 2;
+            let (a_lo, a_hi) = a.split_at(half);
+            let (b_lo, b_hi) = b.split_at(half);
+            let (g_lo, g_hi) = g.split_at(half);
+            let l_i = msm::<E>(g_hi, a_lo) + u * inner_product::<E>(a_lo, b_hi);
+            let r_i = msm::<E>(g_lo, a_hi) + u * inner_product::<E>(a_hi, b_lo);
+            let x = round_challenge::<E>(&mut transcript, l_i, r_i);
+            let x_inv = x.inverse().unwrap();
+
+            a = fold(a_lo, a_hi, x, x_inv);
+            b = fold(b_lo, b_hi, x_inv, x);
+            g = fold(g_lo, g_hi, x_inv, x);
+            l.push(l_i);
+            r.push(r_i);
+        }
+        Ok(IpaProof { l, r, a: a[0] })
+    }
+
+    /// The verifier folds the commitment with the cross terms, C + y * U + sum x^2 * L + x^-2 * R,
+    /// which must be a * G + a * b * U for the folded generator G and power of z b
+    pub fn verify(
+        &self,
+        commitment: E::G1,
+        z: E::ScalarField,
+        y: E::ScalarField,
+        proof: &IpaProof<E>,
+    ) -> bool {
+        let n = self.generators.len();
+        let rounds = proof.l.len();
+        if proof.r.len() != rounds || 1 << rounds != n {
+            return false;
+        }
+        let mut transcript = Transcript::new(TRANSCRIPT_DOMAIN);
+        let u = self.u * bind_evaluation::<E>(&mut transcript, commitment, z, y);
+        let mut folded_commitment = commitment + u * y;
+        let mut challenges = Vec::with_capacity(rounds);
+        for (l_i, r_i) in proof.l.iter().zip(&proof.r) {
+            let x = round_challenge::<E>(&mut transcript, *l_i, *r_i);
+            let x_inv = x.inverse().unwrap();
+            folded_commitment += *l_i * x.square() + *r_i * x_inv.square();
+            challenges.push((x, x_inv));
+        }
+
+        // the folded generator is sum s_i * G_i, where s_i multiplies x or 1/x of each round
+        // depending on the bits of i, the first round being the most significant bit
+        let mut s = vec![E::ScalarField::one()];
+        for (x, x_inv) in challenges.iter().rev() {
+            let lo: Vec<E::ScalarField> = s.iter().map(|s_i| *s_i * x_inv).collect();
+            let hi: Vec<E::ScalarField> = s.iter().map(|s_i| *s_i * x).collect();
+            s = [lo, hi].concat();
+        }
+        let g = E::G1::msm_unchecked(&self.generators, &s);
+        // and the folded b is prod (1/x + x * z^(2^(rounds - 1 - j))) over the rounds j
+        let mut b = E::ScalarField::one();
+        let mut z_power = z;
+        for (x, x_inv) in challenges.iter().rev() {
+            b *= *x_inv + *x * z_power;
+            z_power.square_in_place();
+        }
+        folded_commitment == g * proof.a + u * (proof.a * b)
+    }
+
+    /// Opens `polynomials[i]` over `point_sets[i]`, with the challenges of `KZG::multi_point_open`
+    /// squeezed from the `transcript` once the `commitments` are appended
+    pub fn multi_point_open(
+        &self,
+        polynomials: &[&DensePolynomial<E::ScalarField>],
+        commitments: &[E::G1],
+        point_sets: &[Vec<E::ScalarField>],
+        transcript: &mut Transcript,
+    ) -> Result<IpaBatchProof<E>, SolvencyError> {
+        let one = self.generators[0].into_group();
+        let (w, linearized, z) = shplonk_open::<E, _>(
+            polynomials,
+            commitments,
+            point_sets,
+            one,
+            transcript,
+            |h| self.commit(h),
+        )?;
+        let proof = self.open(&linearized, z, E::ScalarField::zero())?;
+        Ok(IpaBatchProof { w, proof })
+    }
+
+    pub fn verify_multi_point_open(
+        &self,
+        commitments: &[E::G1],
+        point_sets: &[Vec<E::ScalarField>],
+        values: &[Vec<E::ScalarField>],
+        transcript: &mut Transcript,
+        proof: &IpaBatchProof<E>,
+    ) -> bool {
+        let values: Vec<Vec<Evaluation<E>>> = values
+            .iter()
+            .map(|set| set.iter().map(|y| Evaluation::Scalar(*y)).collect())
+            .collect();
+        // the first generator commits to the constant polynomial 1
+        let one = self.generators[0].into_group();
+        match shplonk_commitment::<E>(one, commitments, point_sets, &values, transcript, proof.w) {
+            Some((f, z)) => self.verify(f, z, E::ScalarField::zero(), &proof.proof),
+            None => false,
+        }
+    }
+}
+
+impl<E: Pairing> PolynomialCommitmentScheme for IPA<E> {
+    type Field = E::ScalarField;
+    type Params = IPA<E>;
+    type ProverKey = IPA<E>;
+    type VerifierKey = IPA<E>;
+    type Commitment = E::G1;
+    type Proof = IpaProof<E>;
+    type BatchProof = IpaBatchProof<E>;
+
+    fn trim(
+        params: &IPA<E>,
+        max_degree: usize,
+        _max_points: usize,
+    ) -> Result<(IPA<E>, IPA<E>), SolvencyError> {
+        let key = params.trim(max_degree)?;
+        Ok((key.clone(), key))
+    }
+
+    fn commit(
+        prover_key: &IPA<E>,
+        polynomial: &DensePolynomial<E::ScalarField>,
+    ) -> Result<E::G1, SolvencyError> {
+        prover_key.commit(polynomial)
+    }
+
+    fn open(
+        prover_key: &IPA<E>,
+        polynomial: &DensePolynomial<E::ScalarField>,
+        z: E::ScalarField,
+    ) -> Result<IpaProof<E>, SolvencyError> {
+        prover_key.open(polynomial, z, polynomial.evaluate(&z))
+    }
+
+    fn verify(
+        verifier_key: &IPA<E>,
+        commitment: &E::G1,
+        z: E::ScalarField,
+        y: E::ScalarField,
+        proof: &IpaProof<E>,
+    ) -> bool {
+        verifier_key.verify(*commitment, z, y, proof)
+    }
+
+    fn batch_open(
+        prover_key: &IPA<E>,
+        polynomials: &[&DensePolynomial<E::ScalarField>],
+        commitments: &[E::G1],
+        point_sets: &[Vec<E::ScalarField>],
+        transcript: &mut Transcript,
+    ) -> Result<IpaBatchProof<E>, SolvencyError> {
+        prover_key.multi_point_open(polynomials, commitments, point_sets, transcript)
+    }
+
+    fn batch_verify(
+        verifier_key: &IPA<E>,
+        commitments: &[E::G1],
+        point_sets: &[Vec<E::ScalarField>],
+        values: &[Vec<E::ScalarField>],
+        transcript: &mut Transcript,
+        proof: &IpaBatchProof<E>,
+    ) -> bool {
+        verifier_key.verify_multi_point_open(commitments, point_sets, values, transcript, proof)
+    }
+}
+
+// Try-and-increment: hashes the domain, the index and a counter until the digest is the x
+// coordinate of a point, which is then moved to the prime order subgroup
+fn hash_to_curve<E: Pairing>(domain: &[u8], index: u64) -> E::G1Affine {
+    let mut counter = 0u64;
+    loop {
+        let mut bytes = domain.to_vec();
+        bytes.extend_from_slice(&index.to_le_bytes());
+        bytes.extend_from_slice(&counter.to_le_bytes());
+        if let Some(point) = E::G1Affine::from_random_bytes(&Sha256::digest(&bytes)) {
+            let point = point.mul_by_cofactor();
+            if !point.is_zero() {
+                return point;
+            }
+        }
+        counter += 1;
+    }
+}
+
+// A zero challenge has no inverse, another one is then squeezed
+fn challenge<E: Pairing>(transcript: &mut Transcript, label: &'static [u8]) -> E::ScalarField {
+    loop {
+        let x = transcript.challenge_scalar::<E::ScalarField>(label);
+        if !x.is_zero() {
+            return x;
+        }
+    }
+}
+
+// Challenge xi scaling U, so that the evaluation is bound to the commitment and the point
+fn bind_evaluation<E: Pairing>(
+    transcript: &mut Transcript,
+    commitment: E::G1,
+    z: E::ScalarField,
+    y: E::ScalarField,
+) -> E::ScalarField {
+    transcript.append(b"commitment", &commitment);
+    transcript.append(b"z", &z);
+    transcript.append(b"y", &y);
+    challenge::<E>(transcript, b"xi")
+}
+
+fn round_challenge<E: Pairing>(transcript: &mut Transcript, l: E::G1, r: E::G1) -> E::ScalarField {
+    transcript.append(b"l", &l);
+    transcript.append(b"r", &r);
+    challenge::<E>(transcript, b"x")
+}
+
+fn powers_of<E: Pairing>(z: E::ScalarField, n: usize) -> Vec<E::ScalarField> {
+    let mut powers = Vec::with_capacity(n);
+    let mut power = E::ScalarField::one();
+    for _ in 0..n {
+        powers.push(power);
+        power *= z;
+    }
+    powers
+}
+
+fn inner_product<E: Pairing>(a: &[E::ScalarField], b: &[E::ScalarField]) -> E::ScalarField {
+    a.iter().zip(b).map(|(a_i, b_i)| *a_i * b_i).sum()
+}
+
+fn msm<E: Pairing>(points: &[E::G1], scalars: &[E::ScalarField]) -> E::G1 {
+    E::G1::msm_unchecked(&E::G1::normalize_batch(points), scalars)
+}
+
+// lo * x_lo + hi * x_hi, for scalars as well as points
+fn fold<T, F>(lo: &[T], hi: &[T], x_lo: F, x_hi: F) -> Vec<T>
+where
+    T: Copy + std::ops::Mul<F, Output = T> + std::ops::Add<Output = T>,
+    F: Copy,
+{
+    lo.iter()
+        .zip(hi)
+        .map(|(lo_i, hi_i)| *lo_i * x_lo + *hi_i * x_hi)
+        .collect()
+}
@@ -1,20 +1,99 @@
-use crate::utils::build_zero_polynomial;
+use crate::error::SolvencyError;
+use crate::transcript::Transcript;
+use crate::utils::{
+    build_zero_polynomial, divide_by_zero_polynomial, evaluate_points, interpolate_points,
+    lagrange_coefficients,
+};
 use ark_ec::pairing::Pairing;
-use ark_ff::Field;
-use ark_poly::{univariate::DensePolynomial, DenseUVPolynomial};
-use ark_std::Zero;
+use ark_ec::{AffineRepr, CurveGroup, VariableBaseMSM};
+use ark_ff::{batch_inversion, Field};
+use ark_poly::{univariate::DensePolynomial, DenseUVPolynomial, Polynomial};
+use ark_poly::{EvaluationDomain, GeneralEvaluationDomain};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::rand::Rng;
+use ark_std::{UniformRand, Zero};
+use zeroize::Zeroizing;
 
+#[derive(Debug, Clone, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
 pub struct KZG<E: Pairing> {
     pub g1: E::G1,
     pub g2: E::G2,
     pub degree: usize,
-    pub crs: Vec<E::G1>,
-    pub crs_2: Vec<E::G2>,
+    // SRS points are kept in affine form, which is what the MSM backend expects
+    pub crs: Vec<E::G1Affine>,
+    pub crs_2: Vec<E::G2Affine>,
     pub vk: E::G2,
+    // Degree of the largest power of tau ever published in G1, which may be more than `degree`
+    // when the SRS is read from a larger transcript. Degree bounds are checked against it: a
+    // prover holding the larger powers could shift a polynomial by less than the verifier expects.
+    pub published_degree: usize,
+}
+
+/// What the prover needs to commit and open: the G1 powers of tau
+#[derive(Debug, Clone, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct ProverKey<E: Pairing> {
+    pub powers_of_g1: Vec<E::G1Affine>,
+}
+
+/// What a user needs to check openings. It only holds enough powers of tau to check
+/// multi-openings over at most `powers_of_g2.len() - 1` points.
+#[derive(Debug, Clone, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct VerifierKey<E: Pairing> {
+    pub g1: E::G1,
+    pub g2: E::G2,
+    pub vk: E::G2,
+    // used to commit to the interpolating polynomial L(X) of a multi-opening
+    pub powers_of_g1: Vec<E::G1Affine>,
+    // used to commit to the zero polynomial Z(X) of a multi-opening
+    pub powers_of_g2: Vec<E::G2Affine>,
+}
+
+/// Commitments [L_i(tau)]_1 to the Lagrange basis of an evaluation domain, to commit to and
+/// open polynomials given by their evaluations over the domain
+#[derive(Debug, Clone, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct LagrangeKey<E: Pairing> {
+    pub lagrange_powers: Vec<E::G1Affine>,
+}
+
+/// The claimed value of an opening, either in the clear or as [y]_1
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Evaluation<E: Pairing> {
+    Scalar(E::ScalarField),
+    Encrypted(E::G1),
+}
+
+/// A single-point opening to be checked: `pi` proves that `commitment` opens to `y` at `z`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OpeningClaim<E: Pairing> {
+    pub commitment: E::G1,
+    pub z: E::ScalarField,
+    pub y: Evaluation<E>,
+    pub pi: E::G1,
+}
+
+/// Proof that several polynomials open to given values, each over its own set of points
+/// (SHPLONK, https://eprint.iacr.org/2020/081). `w` commits to the combined quotient and
+/// `w_prime` opens the linearized polynomial at the evaluation challenge.
+#[derive(Debug, Clone, Copy, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct MultiPointProof<E: Pairing> {
+    pub w: E::G1,
+    pub w_prime: E::G1,
+}
+
+/// What a verifier needs to check that committed polynomials have degree at most `bound`.
+/// `max_degree` is the degree of the largest power of tau published in G1.
+#[derive(Debug, Clone, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct DegreeBoundKey<E: Pairing> {
+    pub max_degree: usize,
+    pub bound: usize,
+    pub g2: E::G2,
+    // [tau^(max_degree - bound)]_2
+    pub shifted_g2: E::G2,
 }
 
 impl<E: Pairing> KZG<E> {
-    pub fn new(g1: E::G1, g2: E::G2, degree: usize) -> Self {
+    // Empty SRS, only meant to be filled by setup
+    fn new(g1: E::G1, g2: E::G2, degree: usize) -> Self {
         Self {
             g1,
             g2,
@@ -22,110 +101,1296 @@ impl<E: Pairing> KZG<E> {
             crs: vec![],
             crs_2: vec![],
             vk: g2,
+            published_degree: degree,
         }
     }
 
-    pub fn setup(&mut self, tau: E::ScalarField) {
-        let vk = self.g2 * tau;
-        for pow in 0..self.degree + 1 {
-            let tau_i: E::ScalarField = tau.pow([pow as u64]);
-            let crs_point_g1 = self.g1 * tau_i;
-            let crs_point_g2 = self.g2 * tau_i;
-            self.crs.push(crs_point_g1);
-            self.crs_2.push(crs_point_g2);
+    /// Samples tau locally and builds the SRS from it. Only meant for tests and examples: whoever
+    /// knows tau can forge openings, a real SRS comes from a ceremony (see `ceremony` and `ptau`).
+    pub fn insecure_setup_for_testing<R: Rng>(
+        g1: E::G1,
+        g2: E::G2,
+        degree: usize,
+        rng: &mut R,
+    ) -> Self {
+        let mut kzg = Self::new(g1, g2, degree);
+        kzg.setup(Zeroizing::new(E::ScalarField::rand(rng)));
+        kzg
+    }
+
+    // tau and each of its powers are wiped from memory once the SRS is built
+    fn setup(&mut self, tau: Zeroizing<E::ScalarField>) {
+        let vk = self.g2 * *tau;
+        let mut crs = vec![];
+        let mut crs_2 = vec![];
+        let mut tau_i = Zeroizing::new(E::ScalarField::ONE);
+        for _ in 0..self.degree + 1 {
+            crs.push(self.g1 * *tau_i);
+            crs_2.push(self.g2 * *tau_i);
+            *tau_i *= *tau;
         }
+        // one batched inversion instead of one per point
+        self.crs = E::G1::normalize_batch(&crs);
+        self.crs_2 = E::G2::normalize_batch(&crs_2);
         self.vk = vk;
     }
 
-    pub fn commit(&mut self, polynomial: &DensePolynomial<E::ScalarField>) -> E::G1 {
-        let mut commitment = E::G1::zero();
-        for i in 0..self.degree + 1 {
-            let value = self.crs[i as usize] * polynomial.coeffs[i as usize];
-            commitment += value;
+    /// Builds the SRS from powers of tau computed elsewhere, e.g. read from a Powers-of-Tau
+    /// ceremony transcript. The generators are the first powers and vk is [tau]_2. `crs` must hold
+    /// all the G1 powers published for tau: when it is truncated from a larger transcript,
+    /// `published_degree` has to be set to the degree of the largest one, as `read_ptau` does.
+    pub fn from_powers(
+        crs: Vec<E::G1Affine>,
+        crs_2: Vec<E::G2Affine>,
+    ) -> Result<Self, SolvencyError> {
+        if crs.is_empty() || crs_2.len() < 2 {
+            return Err(SolvencyError::InvalidSrs(format!(
+                "need at least 1 power in G1 and 2 in G2, got {} and {}",
+                crs.len(),
+                crs_2.len()
+            )));
+        }
+        Ok(Self {
+            g1: crs[0].into(),
+            g2: crs_2[0].into(),
+            degree: crs.len() - 1,
+            published_degree: crs.len() - 1,
+            vk: crs_2[1].into(),
+            crs,
+            crs_2,
+        })
+    }
+
+    /// Checks that the SRS is made of successive powers of a single tau: crs[i + 1] = tau * crs[i],
+    /// crs_2[i] holds the same power as crs[i] and vk = crs_2[1]. Each relation is checked for all
+    /// indexes at once with a random linear combination, the first bad index is only looked for
+    /// when a batched check fails.
+    pub fn verify_srs<R: Rng>(&self, rng: &mut R) -> Result<(), SolvencyError> {
+        if self.crs.is_empty() || self.crs_2.len() < 2 {
+            return Err(SolvencyError::InvalidSrs("the SRS is empty".to_string()));
+        }
+        if self.crs_2.len() > self.crs.len() {
+            return Err(SolvencyError::InvalidSrs(
+                "more powers of tau in G2 than in G1".to_string(),
+            ));
+        }
+        if self.g1.is_zero() || self.crs[0] != self.g1.into() {
+            return Err(SolvencyError::SrsMismatch {
+                group: "g1",
+                index: 0,
+            });
+        }
+        if self.g2.is_zero() || self.crs_2[0] != self.g2.into() {
+            return Err(SolvencyError::SrsMismatch {
+                group: "g2",
+                index: 0,
+            });
+        }
+        if self.crs_2[1] != self.vk.into() {
+            return Err(SolvencyError::SrsMismatch {
+                group: "vk",
+                index: 1,
+            });
+        }
+
+        // e(sum r_i * crs[i + 1], g2) == e(sum r_i * crs[i], vk)
+        let r: Vec<E::ScalarField> = (1..self.crs.len())
+            .map(|_| E::ScalarField::rand(rng))
+            .collect();
+        let shifted = E::G1::msm_unchecked(&self.crs[1..], &r);
+        let unshifted = E::G1::msm_unchecked(&self.crs, &r);
+        if E::pairing(shifted, self.g2) != E::pairing(unshifted, self.vk) {
+            let index = (0..self.crs.len() - 1)
+                .find(|&i| E::pairing(self.crs[i + 1], self.g2) != E::pairing(self.crs[i], self.vk))
+                .unwrap_or(0);
+            return Err(SolvencyError::SrsMismatch {
+                group: "g1",
+                index: index + 1,
+            });
+        }
+
+        // e(sum s_i * crs[i], g2) == e(g1, sum s_i * crs_2[i])
+        let n = self.crs_2.len();
+        let s: Vec<E::ScalarField> = (0..n).map(|_| E::ScalarField::rand(rng)).collect();
+        let combined_g1 = E::G1::msm_unchecked(&self.crs[..n], &s);
+        let combined_g2 = E::G2::msm_unchecked(&self.crs_2[..n], &s);
+        if E::pairing(combined_g1, self.g2) != E::pairing(self.g1, combined_g2) {
+            let index = (0..n)
+                .find(|&i| E::pairing(self.crs[i], self.g2) != E::pairing(self.g1, self.crs_2[i]))
+                .unwrap_or(0);
+            return Err(SolvencyError::SrsMismatch {
+                group: "g2",
+                index,
+            });
         }
-        commitment
+        Ok(())
     }
 
-    pub fn open(
+    /// Derives a prover key able to commit to polynomials of degree up to `max_degree` and a
+    /// verifier key able to check multi-openings over up to `max_points` points.
+    pub fn trim(
+        &self,
+        max_degree: usize,
+        max_points: usize,
+    ) -> Result<(ProverKey<E>, VerifierKey<E>), SolvencyError> {
+        let max = max_degree.max(max_points);
+        if max >= self.crs.len() {
+            return Err(SolvencyError::DegreeTooLarge {
+                degree: max,
+                max_degree: self.degree,
+            });
+        }
+        // ptau files hold far fewer powers in G2 than in G1, which only limits the multi-openings
+        if max_points >= self.crs_2.len() {
+            return Err(SolvencyError::InvalidSrs(format!(
+                "multi-openings over {} points need {} powers of tau in G2, the SRS has {}",
+                max_points,
+                max_points + 1,
+                self.crs_2.len()
+            )));
+        }
+        let pk = ProverKey {
+            powers_of_g1: self.crs[..max_degree + 1].to_vec(),
+        };
+        let vk = VerifierKey {
+            g1: self.g1,
+            g2: self.g2,
+            vk: self.vk,
+            powers_of_g1: self.crs[..max_points].to_vec(),
+            powers_of_g2: self.crs_2[..max_points + 1].to_vec(),
+        };
+        Ok((pk, vk))
+    }
+
+    /// Key to check degree bound proofs of `bound`. The shift is taken from the largest power of
+    /// tau ever published, whatever powers the prover claims to hold: anyone holding it could
+    /// otherwise shift a polynomial above the bound by less and pass the check.
+    ///
+    /// Degree bounds can only be checked with an SRS holding every power of its ceremony in both
+    /// groups, e.g. the powers of a [`crate::ceremony`]. An SRS read from a ptau file can not:
+    /// the file has half as many powers in G2 as in G1, and distributed files are cut down from a
+    /// larger ceremony.
+    pub fn degree_bound_key(&self, bound: usize) -> Result<DegreeBoundKey<E>, SolvencyError> {
+        let max_degree = self.published_degree;
+        if bound > max_degree {
+            return Err(SolvencyError::DegreeTooLarge {
+                degree: bound,
+                max_degree,
+            });
+        }
+        let shift = max_degree - bound;
+        let shifted_g2 = self.crs_2.get(shift).ok_or_else(|| {
+            SolvencyError::InvalidSrs(format!(
+                "no [tau^{}]_2 to check degree bounds, which ptau files do not hold",
+                shift
+            ))
+        })?;
+        Ok(DegreeBoundKey {
+            max_degree,
+            bound,
+            g2: self.g2,
+            shifted_g2: shifted_g2.into_group(),
+        })
+    }
+
+    /// Proof that `polynomial` has degree at most `bound`: its commitment shifted by
+    /// X^(published_degree - bound), which only fits the powers of tau if the bound holds. It
+    /// needs all the published powers, so it fails on an SRS truncated from a larger transcript,
+    /// such as one read from a ptau file (see [`KZG::degree_bound_key`]).
+    pub fn prove_degree_bound(
+        &self,
+        polynomial: &DensePolynomial<E::ScalarField>,
+        bound: usize,
+    ) -> Result<E::G1, SolvencyError> {
+        if polynomial.degree() > bound {
+            return Err(SolvencyError::DegreeTooLarge {
+                degree: polynomial.degree(),
+                max_degree: bound,
+            });
+        }
+        if bound > self.published_degree {
+            return Err(SolvencyError::DegreeTooLarge {
+                degree: bound,
+                max_degree: self.published_degree,
+            });
+        }
+        if self.published_degree >= self.crs.len() {
+            return Err(SolvencyError::InvalidSrs(format!(
+                "degree bound proofs need the {} published powers of tau, only {} are loaded",
+                self.published_degree + 1,
+                self.crs.len()
+            )));
+        }
+        let shift = self.published_degree - bound;
+        commit_coeffs::<E>(&self.crs[shift..], &polynomial.coeffs)
+    }
+}
+
+/// Commitments and openings, written once over the G1 powers of tau of a key. Implemented by
+/// the full `KZG` setup and by its trimmed `ProverKey`.
+pub trait KzgProver<E: Pairing> {
+    fn powers_of_g1(&self) -> &[E::G1Affine];
+
+    /// Lagrange-basis key over the evaluation domain of size `domain_size`
+    fn lagrange_key(&self, domain_size: usize) -> Result<LagrangeKey<E>, SolvencyError> {
+        lagrange_key::<E>(self.powers_of_g1(), domain_size)
+    }
+
+    fn commit(&self, polynomial: &DensePolynomial<E::ScalarField>) -> Result<E::G1, SolvencyError> {
+        commit_coeffs::<E>(self.powers_of_g1(), &polynomial.coeffs)
+    }
+
+    fn open(
         &self,
         polynomial: &DensePolynomial<E::ScalarField>,
         z: E::ScalarField,
         y: E::ScalarField,
-    ) -> E::G1 {
-        // Opening at y = p(z). Notation from here: https://hackmd.io/@gnark/kzg-bls24
-        let y_polynomial = DensePolynomial::from_coefficients_vec(vec![y]);
-        let numerator = polynomial - &y_polynomial;
-        let denominator = DensePolynomial::from_coefficients_vec(vec![-z, E::ScalarField::ONE]);
-        let q_x = &numerator / &denominator;
-        let mut pi = E::G1::zero();
-        for (i, coeff) in q_x.coeffs.iter().enumerate() {
-            pi += self.crs[i] * coeff;
-        }
-        pi
+    ) -> Result<E::G1, SolvencyError> {
+        open::<E>(self.powers_of_g1(), polynomial, z, y)
     }
 
-    pub fn multi_open(
+    /// Hiding commitment to `polynomial`, blinded over the evaluation domain of size
+    /// `domain_size` by a random multiple of degree `hiding_bound` of its vanishing polynomial,
+    /// see `blind`. Returns the blinded polynomial, which is opened with `open` like any other.
+    fn commit_hiding<R: Rng>(
+        &self,
+        polynomial: &DensePolynomial<E::ScalarField>,
+        domain_size: usize,
+        hiding_bound: usize,
+        rng: &mut R,
+    ) -> Result<(E::G1, DensePolynomial<E::ScalarField>), SolvencyError> {
+        let blinded = blind::<E, R>(polynomial, domain_size, hiding_bound, rng)?;
+        Ok((self.commit(&blinded)?, blinded))
+    }
+
+    fn multi_open(
         &self,
         polynomial: &DensePolynomial<E::ScalarField>,
         lagrange_polynomial: &DensePolynomial<E::ScalarField>,
         z_values: Vec<E::ScalarField>,
-    ) -> E::G1 {
-        let zero_polynomial = build_zero_polynomial::<E>(&z_values);
-        let q = &(polynomial - lagrange_polynomial) / &zero_polynomial;
-        let mut pi = self.g1 * E::ScalarField::ZERO;
-        for (i, coeff) in q.coeffs.iter().enumerate() {
-            pi += self.crs[i] * coeff;
-        }
-        pi
+    ) -> Result<E::G1, SolvencyError> {
+        multi_open::<E>(
+            self.powers_of_g1(),
+            polynomial,
+            lagrange_polynomial,
+            &z_values,
+        )
     }
 
-    pub fn verify(
+    /// Opens several polynomials at the same point `z` with a single proof. Their quotients are
+    /// combined with powers of a challenge `gamma`, squeezed from the `transcript` once the
+    /// `commitments` to the polynomials, `z` and the values are appended.
+    fn batch_open(
         &self,
-        y: E::ScalarField,
+        polynomials: &[&DensePolynomial<E::ScalarField>],
+        commitments: &[E::G1],
         z: E::ScalarField,
-        commitment: E::G1,
-        pi: E::G1,
-    ) -> bool {
-        let py = self.g1 * y;
-        let pz = self.g2 * z;
-        let lhs = E::pairing(pi, self.vk - pz);
-        let rhs = E::pairing(commitment - py, self.g2);
-        lhs == rhs
+        transcript: &mut Transcript,
+    ) -> Result<E::G1, SolvencyError> {
+        batch_open::<E>(self.powers_of_g1(), polynomials, commitments, z, transcript)
+    }
+
+    /// Opens `polynomials[i]` over `point_sets[i]` with a two elements proof. The challenges are
+    /// squeezed from the `transcript` once the `commitments` to the polynomials, the points and
+    /// the values are appended.
+    fn multi_point_open(
+        &self,
+        polynomials: &[&DensePolynomial<E::ScalarField>],
+        commitments: &[E::G1],
+        point_sets: &[Vec<E::ScalarField>],
+        transcript: &mut Transcript,
+    ) -> Result<MultiPointProof<E>, SolvencyError> {
+        multi_point_open::<E>(
+            self.powers_of_g1(),
+            polynomials,
+            commitments,
+            point_sets,
+            transcript,
+        )
+    }
+
+    /// Opening proofs of `polynomial` at every point of the evaluation domain of size
+    /// `domain_size`, computed at once in O(n log n) (Feist-Khovratovich,
+    /// https://eprint.iacr.org/2023/033). The proof at index k opens the polynomial at ω^k.
+    fn open_all(
+        &self,
+        polynomial: &DensePolynomial<E::ScalarField>,
+        domain_size: usize,
+    ) -> Result<Vec<E::G1>, SolvencyError> {
+        open_all::<E>(self.powers_of_g1(), polynomial, domain_size)
+    }
+
+    /// Proofs of the (username, balance) rows (2i, 2i + 1) of every user i < `n_users`, each of
+    /// them checked with `verify_multi_open` over the two rows
+    fn open_all_users(
+        &self,
+        polynomial: &DensePolynomial<E::ScalarField>,
+        domain_size: usize,
+        n_users: usize,
+    ) -> Result<Vec<E::G1>, SolvencyError> {
+        open_all_users::<E>(self.powers_of_g1(), polynomial, domain_size, n_users)
+    }
+}
+
+/// Checks of the openings made with `KzgProver`, written once for the full `KZG` setup and its
+/// trimmed `VerifierKey`
+pub trait KzgVerifier<E: Pairing> {
+    fn g1(&self) -> E::G1;
+
+    fn g2(&self) -> E::G2;
+
+    /// [tau]_2
+    fn vk(&self) -> E::G2;
+
+    /// Powers of tau in G1 and G2 to commit to the L(X) and Z(X) of a multi-opening
+    fn multi_open_powers(&self) -> (&[E::G1Affine], &[E::G2Affine]);
+
+    fn verify(&self, y: E::ScalarField, z: E::ScalarField, commitment: E::G1, pi: E::G1) -> bool {
+        check_opening::<E>(self.g2(), self.vk(), self.g1() * y, z, commitment, pi)
     }
 
-    pub fn verify_from_encrypted_y(
+    fn verify_from_encrypted_y(
         &self,
         py: E::G1,
         z: E::ScalarField,
         commitment: E::G1,
         pi: E::G1,
     ) -> bool {
-        let pz = self.g2 * z;
-        let lhs = E::pairing(pi, self.vk - pz);
-        let rhs = E::pairing(commitment - py, self.g2);
-        lhs == rhs
+        check_opening::<E>(self.g2(), self.vk(), py, z, commitment, pi)
     }
 
-    pub fn verify_multi_open(
+    /// Checks a multi-opening. With a trimmed key, `lagrange_polynomial` must be the lowest
+    /// degree interpolant of the opened values, so that it fits in the bounded powers.
+    fn verify_multi_open(
         &self,
         commitment: E::G1,
         pi: E::G1,
         zero_polynomial: &DensePolynomial<E::ScalarField>,
         lagrange_polynomial: &DensePolynomial<E::ScalarField>,
     ) -> bool {
-        let mut pz = self.g2 * E::ScalarField::ZERO;
-        for (i, coeff) in zero_polynomial.coeffs.iter().enumerate() {
-            pz += self.crs_2[i] * coeff;
+        let (powers_of_g1, powers_of_g2) = self.multi_open_powers();
+        check_multi_opening::<E>(
+            powers_of_g1,
+            powers_of_g2,
+            self.g2(),
+            commitment,
+            pi,
+            zero_polynomial,
+            lagrange_polynomial,
+        )
+    }
+
+    /// Checks a `batch_open` proof that the `commitments` open to `values` at `z`, replaying the
+    /// prover's `transcript`
+    fn verify_batch_open(
+        &self,
+        commitments: &[E::G1],
+        values: &[E::ScalarField],
+        z: E::ScalarField,
+        transcript: &mut Transcript,
+        pi: E::G1,
+    ) -> bool {
+        check_batch_opening::<E>(
+            self.g1(),
+            self.g2(),
+            self.vk(),
+            commitments,
+            values,
+            z,
+            transcript,
+            pi,
+        )
+    }
+
+    /// Checks a `multi_point_open` proof that `commitments[i]` opens to `values[i]` over
+    /// `point_sets[i]`, replaying the prover's `transcript`
+    fn verify_multi_point_open(
+        &self,
+        commitments: &[E::G1],
+        point_sets: &[Vec<E::ScalarField>],
+        values: &[Vec<Evaluation<E>>],
+        transcript: &mut Transcript,
+        proof: &MultiPointProof<E>,
+    ) -> bool {
+        check_multi_point_opening::<E>(
+            self.g1(),
+            self.g2(),
+            self.vk(),
+            commitments,
+            point_sets,
+            values,
+            transcript,
+            proof,
+        )
+    }
+
+    /// Checks all the claims at once with a single multi-pairing, see `batch_check`. The claims
+    /// are appended to the `transcript` to derive the folding challenge.
+    fn batch_verify(&self, claims: &[OpeningClaim<E>], transcript: &mut Transcript) -> bool {
+        batch_check::<E>(self.g1(), self.g2(), self.vk(), claims, transcript)
+    }
+
+    /// Returns the indices of the claims that do not hold, empty if they all do
+    fn batch_verify_failures(
+        &self,
+        claims: &[OpeningClaim<E>],
+        transcript: &mut Transcript,
+    ) -> Vec<usize> {
+        batch_failures::<E>(self.g1(), self.g2(), self.vk(), claims, 0, transcript)
+    }
+}
+
+impl<E: Pairing> KzgProver<E> for KZG<E> {
+    fn powers_of_g1(&self) -> &[E::G1Affine] {
+        &self.crs
+    }
+}
+
+impl<E: Pairing> KzgVerifier<E> for KZG<E> {
+    fn g1(&self) -> E::G1 {
+        self.g1
+    }
+
+    fn g2(&self) -> E::G2 {
+        self.g2
+    }
+
+    fn vk(&self) -> E::G2 {
+        self.vk
+    }
+
+    fn multi_open_powers(&self) -> (&[E::G1Affine], &[E::G2Affine]) {
+        (&self.crs, &self.crs_2)
+    }
+}
+
+impl<E: Pairing> ProverKey<E> {
+    pub fn max_degree(&self) -> Result<usize, SolvencyError> {
+        largest_power(&self.powers_of_g1, "g1")
+    }
+}
+
+impl<E: Pairing> KzgProver<E> for ProverKey<E> {
+    fn powers_of_g1(&self) -> &[E::G1Affine] {
+        &self.powers_of_g1
+    }
+}
+
+impl<E: Pairing> VerifierKey<E> {
+    /// Maximum number of points a multi-opening checked with this key can cover
+    pub fn max_points(&self) -> Result<usize, SolvencyError> {
+        largest_power(&self.powers_of_g2, "g2")
+    }
+}
+
+impl<E: Pairing> KzgVerifier<E> for VerifierKey<E> {
+    fn g1(&self) -> E::G1 {
+        self.g1
+    }
+
+    fn g2(&self) -> E::G2 {
+        self.g2
+    }
+
+    fn vk(&self) -> E::G2 {
+        self.vk
+    }
+
+    fn multi_open_powers(&self) -> (&[E::G1Affine], &[E::G2Affine]) {
+        (&self.powers_of_g1, &self.powers_of_g2)
+    }
+}
+
+impl<E: Pairing> LagrangeKey<E> {
+    pub fn domain_size(&self) -> usize {
+        self.lagrange_powers.len()
+    }
+
+    /// Commits to the polynomial taking the values `evaluations` over the domain. Missing
+    /// evaluations are zeroes, as in `lagrange_interpolate`.
+    pub fn commit_evaluations(
+        &self,
+        evaluations: &[E::ScalarField],
+    ) -> Result<E::G1, SolvencyError> {
+        self.check_evaluations(evaluations)?;
+        Ok(E::G1::msm_unchecked(&self.lagrange_powers, evaluations))
+    }
+
+    /// Opening proof at ω^index of the polynomial taking the values `evaluations` over the
+    /// domain, checked with `verify` like the coefficient form one
+    pub fn open_evaluations(
+        &self,
+        evaluations: &[E::ScalarField],
+        index: usize,
+    ) -> Result<E::G1, SolvencyError> {
+        self.multi_open_evaluations(evaluations, &[index])
+    }
+
+    /// Multi-opening at the ω^index for all `indexes`, checked with `verify_multi_open`
+    pub fn multi_open_evaluations(
+        &self,
+        evaluations: &[E::ScalarField],
+        indexes: &[usize],
+    ) -> Result<E::G1, SolvencyError> {
+        self.check_evaluations(evaluations)?;
+        let n = self.domain_size();
+        if indexes.is_empty() {
+            return Err(SolvencyError::EmptyRoots);
+        }
+        if let Some(index) = indexes.iter().find(|index| **index >= n) {
+            return Err(SolvencyError::IndexOutOfRange {
+                index: *index,
+                size: n,
+            });
+        }
+        let mut evaluations = evaluations.to_vec();
+        evaluations.resize(n, E::ScalarField::zero());
+        let domain = GeneralEvaluationDomain::<E::ScalarField>::new(n)
+            .ok_or(SolvencyError::InvalidDomainSize(n))?;
+        let elements: Vec<E::ScalarField> = domain.elements().collect();
+
+        // the multi-opening quotient is sum c_k * q_k, see combine_openings
+        let points: Vec<E::ScalarField> = indexes.iter().map(|k| elements[*k]).collect();
+        let coefficients = lagrange_denominators::<E>(&points)?;
+        let mut quotient = vec![E::ScalarField::zero(); n];
+        for (k, c) in indexes.iter().zip(coefficients) {
+            let q = quotient_evaluations::<E>(&evaluations, &elements, *k);
+            for (acc, q_i) in quotient.iter_mut().zip(q) {
+                *acc += c * q_i;
+            }
+        }
+        Ok(E::G1::msm_unchecked(&self.lagrange_powers, &quotient))
+    }
+
+    /// Commitment after adding `delta` to the evaluation at each (row, delta) of `changes`,
+    /// in O(changes) group operations
+    pub fn update_commitment(
+        &self,
+        commitment: E::G1,
+        changes: &[(usize, E::ScalarField)],
+    ) -> Result<E::G1, SolvencyError> {
+        let (rows, deltas) = self.split_changes(changes)?;
+        let powers: Vec<E::G1Affine> = rows.iter().map(|i| self.lagrange_powers[*i]).collect();
+        Ok(commitment + E::G1::msm_unchecked(&powers, &deltas))
+    }
+
+    /// Refreshes a cached opening proof at ω^index after `changes`. A change at row i != index
+    /// adds delta * ([L_i] - ω^(i - index) [L_index]) / (ω^i - ω^index), which is O(1). A change
+    /// of the opened row itself adds delta * [(L_index(X) - 1) / (X - ω^index)], an O(n) MSM.
+    pub fn update_opening(
+        &self,
+        proof: E::G1,
+        index: usize,
+        changes: &[(usize, E::ScalarField)],
+    ) -> Result<E::G1, SolvencyError> {
+        let n = self.domain_size();
+        if index >= n {
+            return Err(SolvencyError::IndexOutOfRange { index, size: n });
+        }
+        let (rows, deltas) = self.split_changes(changes)?;
+        let domain = GeneralEvaluationDomain::<E::ScalarField>::new(n)
+            .ok_or(SolvencyError::InvalidDomainSize(n))?;
+        let x = domain.element(index);
+
+        let mut powers = Vec::with_capacity(rows.len() + 1);
+        let mut scalars = Vec::with_capacity(rows.len() + 1);
+        let mut index_scalar = E::ScalarField::zero();
+        let mut index_delta = E::ScalarField::zero();
+        for (i, delta) in rows.iter().zip(deltas) {
+            if *i == index {
+                index_delta += delta;
+                continue;
+            }
+            let scale = delta
+                * (domain.element(*i) - x)
+                    .inverse()
+                    .ok_or(SolvencyError::DuplicatePoints)?;
+            powers.push(self.lagrange_powers[*i]);
+            scalars.push(scale);
+            index_scalar -= scale * domain.element((*i + n - index) % n);
+        }
+        powers.push(self.lagrange_powers[index]);
+        scalars.push(index_scalar);
+        let mut updated = proof + E::G1::msm_unchecked(&powers, &scalars);
+
+        if !index_delta.is_zero() {
+            let mut unit = vec![E::ScalarField::zero(); n];
+            unit[index] = E::ScalarField::ONE;
+            let elements: Vec<E::ScalarField> = domain.elements().collect();
+            let quotient = quotient_evaluations::<E>(&unit, &elements, index);
+            updated += E::G1::msm_unchecked(&self.lagrange_powers, &quotient) * index_delta;
+        }
+        Ok(updated)
+    }
+
+    /// Refreshes the cached per-user proofs of `open_all_users` (rows (2i, 2i + 1) of user i)
+    /// after `changes`
+    pub fn update_user_proofs(
+        &self,
+        proofs: &[E::G1],
+        changes: &[(usize, E::ScalarField)],
+    ) -> Result<Vec<E::G1>, SolvencyError> {
+        let domain = GeneralEvaluationDomain::<E::ScalarField>::new(self.domain_size())
+            .ok_or(SolvencyError::InvalidDomainSize(self.domain_size()))?;
+        proofs
+            .iter()
+            .enumerate()
+            .map(|(i, proof)| {
+                // a user proof is linear in the single-row proofs, so are their updates
+                let updates = [
+                    self.update_opening(E::G1::zero(), 2 * i, changes)?,
+                    self.update_opening(E::G1::zero(), 2 * i + 1, changes)?,
+                ];
+                let points = [domain.element(2 * i), domain.element(2 * i + 1)];
+                Ok(*proof + combine_openings::<E>(&points, &updates)?)
+            })
+            .collect()
+    }
+
+    fn split_changes(
+        &self,
+        changes: &[(usize, E::ScalarField)],
+    ) -> Result<(Vec<usize>, Vec<E::ScalarField>), SolvencyError> {
+        let n = self.domain_size();
+        if let Some((index, _)) = changes.iter().find(|(index, _)| *index >= n) {
+            return Err(SolvencyError::IndexOutOfRange {
+                index: *index,
+                size: n,
+            });
+        }
+        Ok(changes.iter().copied().unzip())
+    }
+
+    fn check_evaluations(&self, evaluations: &[E::ScalarField]) -> Result<(), SolvencyError> {
+        if evaluations.len() > self.domain_size() {
+            return Err(SolvencyError::LengthMismatch {
+                expected: self.domain_size(),
+                found: evaluations.len(),
+            });
+        }
+        Ok(())
+    }
+}
+
+impl<E: Pairing> DegreeBoundKey<E> {
+    /// e(commitment, [tau^(max_degree - bound)]_2) == e(proof, g2)
+    pub fn verify(&self, commitment: E::G1, proof: E::G1) -> bool {
+        E::multi_pairing([commitment, -proof], [self.shifted_g2, self.g2]).is_zero()
+    }
+
+    /// Checks the degree bound of several commitments at once, e.g. of P(X) and I(X)
+    pub fn verify_all(&self, commitments: &[E::G1], proofs: &[E::G1]) -> bool {
+        commitments.len() == proofs.len()
+            && commitments
+                .iter()
+                .zip(proofs)
+                .all(|(commitment, proof)| self.verify(*commitment, *proof))
+    }
+}
+
+// Commits to a coefficient vector, making sure it fits within the given powers of tau
+fn commit_coeffs<E: Pairing>(
+    powers: &[E::G1Affine],
+    coeffs: &[E::ScalarField],
+) -> Result<E::G1, SolvencyError> {
+    check_degree::<E>(powers, coeffs)?;
+    // Pippenger MSM of the coefficients against the first coeffs.len() powers of tau
+    Ok(E::G1::msm_unchecked(powers, coeffs))
+}
+
+// A polynomial can only be committed to, and thus opened, if it fits within the powers of tau
+fn check_degree<E: Pairing>(
+    powers: &[E::G1Affine],
+    coeffs: &[E::ScalarField],
+) -> Result<(), SolvencyError> {
+    let max_degree = largest_power(powers, "g1")?;
+    if coeffs.len() > powers.len() {
+        return Err(SolvencyError::DegreeTooLarge {
+            degree: coeffs.len() - 1,
+            max_degree,
+        });
+    }
+    Ok(())
+}
+
+// Exponent of the largest power of tau. Keys are deserialized from untrusted bytes, so the powers
+// may be empty.
+fn largest_power<T>(powers: &[T], group: &str) -> Result<usize, SolvencyError> {
+    powers
+        .len()
+        .checked_sub(1)
+        .ok_or_else(|| SolvencyError::InvalidSrs(format!("no powers of tau in {}", group)))
+}
+
+fn open<E: Pairing>(
+    powers: &[E::G1Affine],
+    polynomial: &DensePolynomial<E::ScalarField>,
+    z: E::ScalarField,
+    y: E::ScalarField,
+) -> Result<E::G1, SolvencyError> {
+    check_degree::<E>(powers, &polynomial.coeffs)?;
+    // Opening at y = p(z). Notation from here: https://hackmd.io/@gnark/kzg-bls24
+    let y_polynomial = DensePolynomial::from_coefficients_vec(vec![y]);
+    let numerator = polynomial - &y_polynomial;
+    let denominator = DensePolynomial::from_coefficients_vec(vec![-z, E::ScalarField::ONE]);
+    let q_x = &numerator / &denominator;
+    commit_coeffs::<E>(powers, &q_x.coeffs)
+}
+
+// f(X) + b(X) * Z_H(X) with b random of degree `hiding_bound` and Z_H the vanishing polynomial of
+// the domain H. It takes the same values as f over H, so that the rows opened over H do not
+// change, while its commitment is uniformly random and needs no second trapdoor. Openings at
+// points outside of H reveal evaluations of b too, the polynomial stays hiding after at most
+// `hiding_bound` of them.
+fn blind<E: Pairing, R: Rng>(
+    polynomial: &DensePolynomial<E::ScalarField>,
+    domain_size: usize,
+    hiding_bound: usize,
+    rng: &mut R,
+) -> Result<DensePolynomial<E::ScalarField>, SolvencyError> {
+    let domain = GeneralEvaluationDomain::<E::ScalarField>::new(domain_size)
+        .ok_or(SolvencyError::InvalidDomainSize(domain_size))?;
+    let vanishing: DensePolynomial<E::ScalarField> = domain.vanishing_polynomial().into();
+    let blinding_polynomial = DensePolynomial::rand(hiding_bound, rng);
+    Ok(polynomial + &(&blinding_polynomial * &vanishing))
+}
+
+fn multi_open<E: Pairing>(
+    powers: &[E::G1Affine],
+    polynomial: &DensePolynomial<E::ScalarField>,
+    lagrange_polynomial: &DensePolynomial<E::ScalarField>,
+    z_values: &[E::ScalarField],
+) -> Result<E::G1, SolvencyError> {
+    check_degree::<E>(powers, &polynomial.coeffs)?;
+    let zero_polynomial = build_zero_polynomial::<E>(z_values)?;
+    let q = divide_by_zero_polynomial::<E>(&(polynomial - lagrange_polynomial), &zero_polynomial);
+    commit_coeffs::<E>(powers, &q.coeffs)
+}
+
+// Opening of sum gamma^i * f_i(X) at z, whose quotient is sum gamma^i * (f_i(X) - f_i(z)) / (X - z)
+fn batch_open<E: Pairing>(
+    powers: &[E::G1Affine],
+    polynomials: &[&DensePolynomial<E::ScalarField>],
+    commitments: &[E::G1],
+    z: E::ScalarField,
+    transcript: &mut Transcript,
+) -> Result<E::G1, SolvencyError> {
+    if polynomials.len() != commitments.len() {
+        return Err(SolvencyError::LengthMismatch {
+            expected: polynomials.len(),
+            found: commitments.len(),
+        });
+    }
+    let values: Vec<E::ScalarField> = polynomials.iter().map(|f| f.evaluate(&z)).collect();
+    let gamma = batch_challenge::<E>(transcript, commitments, z, &values);
+    let mut combined = DensePolynomial::zero();
+    for polynomial in polynomials.iter().rev() {
+        combined = &(&combined * gamma) + *polynomial;
+    }
+    let y = combined.evaluate(&z);
+    open::<E>(powers, &combined, z, y)
+}
+
+// With r_i the interpolant of f_i over S_i and Z_i the zero polynomial of S_i, the prover commits
+// to h(X) = sum gamma^i * (f_i(X) - r_i(X)) / Z_i(X). At the challenge z, the polynomial
+// L(X) = sum gamma^i * (f_i(X) - r_i(z)) / Z_i(z) - h(X) vanishes, and W' opens it at z.
+fn multi_point_open<E: Pairing>(
+    powers: &[E::G1Affine],
+    polynomials: &[&DensePolynomial<E::ScalarField>],
+    commitments: &[E::G1],
+    point_sets: &[Vec<E::ScalarField>],
+    transcript: &mut Transcript,
+) -> Result<MultiPointProof<E>, SolvencyError> {
+    let one = powers
+        .first()
+        .ok_or_else(|| SolvencyError::InvalidSrs("the SRS is empty".to_string()))?;
+    let (w, linearized, z) = shplonk_open::<E, _>(
+        polynomials,
+        commitments,
+        point_sets,
+        one.into_group(),
+        transcript,
+        |h| commit_coeffs::<E>(powers, &h.coeffs),
+    )?;
+    let w_prime = open::<E>(powers, &linearized, z, E::ScalarField::zero())?;
+    Ok(MultiPointProof { w, w_prime })
+}
+
+// W = commit(h), L(X) and the evaluation challenge z of a multi-point opening
+pub(crate) type ShplonkOpening<E> = (
+    <E as Pairing>::G1,
+    DensePolynomial<<E as Pairing>::ScalarField>,
+    <E as Pairing>::ScalarField,
+);
+
+// W, L(X) and z of multi_point_open, with `commit` committing to h(X). They only rely on the
+// commitments being additively homomorphic, so other backends reduce their multi-point openings
+// to a single opening the same way. `one` commits to the constant polynomial 1.
+pub(crate) fn shplonk_open<E, C>(
+    polynomials: &[&DensePolynomial<E::ScalarField>],
+    commitments: &[E::G1],
+    point_sets: &[Vec<E::ScalarField>],
+    one: E::G1,
+    transcript: &mut Transcript,
+    commit: C,
+) -> Result<ShplonkOpening<E>, SolvencyError>
+where
+    E: Pairing,
+    C: FnOnce(&DensePolynomial<E::ScalarField>) -> Result<E::G1, SolvencyError>,
+{
+    if polynomials.len() != point_sets.len() {
+        return Err(SolvencyError::LengthMismatch {
+            expected: polynomials.len(),
+            found: point_sets.len(),
+        });
+    }
+    if polynomials.len() != commitments.len() {
+        return Err(SolvencyError::LengthMismatch {
+            expected: polynomials.len(),
+            found: commitments.len(),
+        });
+    }
+    let values: Vec<Vec<E::ScalarField>> = polynomials
+        .iter()
+        .zip(point_sets)
+        .map(|(polynomial, points)| evaluate_points::<E>(polynomial, points))
+        .collect();
+    let encrypted: Vec<Vec<E::G1>> = values
+        .iter()
+        .map(|set| set.iter().map(|y| one * y).collect())
+        .collect();
+    let gamma = shplonk_gamma::<E>(transcript, commitments, point_sets, &encrypted);
+
+    let mut h = DensePolynomial::zero();
+    let mut interpolants = Vec::with_capacity(polynomials.len());
+    let mut gamma_i = E::ScalarField::ONE;
+    for ((polynomial, points), values) in polynomials.iter().zip(point_sets).zip(&values) {
+        let interpolant = interpolate_points::<E>(points, values)?;
+        let zero_polynomial = build_zero_polynomial::<E>(points)?;
+        let quotient =
+            divide_by_zero_polynomial::<E>(&(*polynomial - &interpolant), &zero_polynomial);
+        h = &h + &(&quotient * gamma_i);
+        interpolants.push((interpolant, zero_polynomial));
+        gamma_i *= gamma;
+    }
+    let w = commit(&h)?;
+    transcript.append(b"w", &w);
+    let z = transcript.challenge_scalar::<E::ScalarField>(b"z");
+
+    let mut linearized = DensePolynomial::zero();
+    let mut gamma_i = E::ScalarField::ONE;
+    for (polynomial, (interpolant, zero_polynomial)) in polynomials.iter().zip(&interpolants) {
+        // 1 / Z_i(z), which does not exist if z is one of the opened points
+        let scale = zero_polynomial
+            .evaluate(&z)
+            .inverse()
+            .ok_or(SolvencyError::DuplicatePoints)?;
+        let shifted = *polynomial
+            - &DensePolynomial::from_coefficients_vec(vec![interpolant.evaluate(&z)]);
+        linearized = &linearized + &(&shifted * (gamma_i * scale));
+        gamma_i *= gamma;
+    }
+    let linearized = &linearized - &h;
+    Ok((w, linearized, z))
+}
+
+// The commitments, points and values are all appended before gamma is squeezed, so that none of
+// them can be chosen after the challenges. The values are appended as [y]_1, so that the prover
+// and a verifier only holding encrypted values derive the same challenges.
+fn shplonk_gamma<E: Pairing>(
+    transcript: &mut Transcript,
+    commitments: &[E::G1],
+    point_sets: &[Vec<E::ScalarField>],
+    values: &[Vec<E::G1>],
+) -> E::ScalarField {
+    transcript.append(b"commitments", commitments);
+    for (points, values) in point_sets.iter().zip(values) {
+        transcript.append(b"points", points);
+        transcript.append(b"values", values);
+    }
+    transcript.challenge_scalar(b"gamma")
+}
+
+// Challenge gamma of batch_open, once the commitments, the opened point and the values are
+// appended
+fn batch_challenge<E: Pairing>(
+    transcript: &mut Transcript,
+    commitments: &[E::G1],
+    z: E::ScalarField,
+    values: &[E::ScalarField],
+) -> E::ScalarField {
+    transcript.append(b"commitments", commitments);
+    transcript.append(b"z", &z);
+    transcript.append(b"values", values);
+    transcript.challenge_scalar(b"gamma")
+}
+
+/// Combines the single-point openings `proofs[k]` at `points[k]` into the multi-opening over all
+/// the points, as sum c_k * proofs[k] with c_k = 1 / prod_{l != k} (points[k] - points[l])
+pub fn combine_openings<E: Pairing>(
+    points: &[E::ScalarField],
+    proofs: &[E::G1],
+) -> Result<E::G1, SolvencyError> {
+    if points.len() != proofs.len() {
+        return Err(SolvencyError::LengthMismatch {
+            expected: points.len(),
+            found: proofs.len(),
+        });
+    }
+    let coefficients = lagrange_denominators::<E>(points)?;
+    let proofs = E::G1::normalize_batch(proofs);
+    Ok(E::G1::msm_unchecked(&proofs, &coefficients))
+}
+
+// 1 / prod_{l != k} (points[k] - points[l]) for every k
+fn lagrange_denominators<E: Pairing>(
+    points: &[E::ScalarField],
+) -> Result<Vec<E::ScalarField>, SolvencyError> {
+    let mut coefficients = Vec::with_capacity(points.len());
+    for (k, point) in points.iter().enumerate() {
+        let mut denominator = E::ScalarField::ONE;
+        for (l, other) in points.iter().enumerate() {
+            if k != l {
+                denominator *= *point - other;
+            }
         }
+        coefficients.push(denominator.inverse().ok_or(SolvencyError::DuplicatePoints)?);
+    }
+    Ok(coefficients)
+}
+
+// [L_i(tau)]_1 = 1/n * sum_j ω^(-ij) [tau^j]_1, i.e. the inverse FFT of the powers of tau
+fn lagrange_key<E: Pairing>(
+    powers: &[E::G1Affine],
+    domain_size: usize,
+) -> Result<LagrangeKey<E>, SolvencyError> {
+    let domain = GeneralEvaluationDomain::<E::ScalarField>::new(domain_size)
+        .ok_or(SolvencyError::InvalidDomainSize(domain_size))?;
+    let n = domain.size();
+    let max_degree = largest_power(powers, "g1")?;
+    if n > powers.len() {
+        return Err(SolvencyError::DegreeTooLarge {
+            degree: n - 1,
+            max_degree,
+        });
+    }
+    let mut lagrange_powers: Vec<E::G1> = powers[..n].iter().map(|p| p.into_group()).collect();
+    domain.ifft_in_place(&mut lagrange_powers);
+    Ok(LagrangeKey {
+        lagrange_powers: E::G1::normalize_batch(&lagrange_powers),
+    })
+}
+
+// Evaluations over the domain of the quotient (f(X) - f(ω^k)) / (X - ω^k):
+// q_i = (f_i - f_k) / (ω^i - ω^k) for i != k, and at ω^k the derivative
+// q_k = f'(ω^k) = sum_{j != k} (f_j - f_k) * ω^(j - k) / (ω^k - ω^j)
+fn quotient_evaluations<E: Pairing>(
+    evaluations: &[E::ScalarField],
+    elements: &[E::ScalarField],
+    k: usize,
+) -> Vec<E::ScalarField> {
+    let n = elements.len();
+    let mut inverses: Vec<E::ScalarField> = elements.iter().map(|x| *x - elements[k]).collect();
+    inverses[k] = E::ScalarField::ONE;
+    batch_inversion(&mut inverses);
+    let mut q = vec![E::ScalarField::zero(); n];
+    for i in (0..n).filter(|i| *i != k) {
+        let difference = evaluations[i] - evaluations[k];
+        q[i] = difference * inverses[i];
+        // ω^(i - k) = ω^i * ω^(-k), and ω^(-k) = ω^(n - k)
+        q[k] -= difference * elements[i] * elements[(n - k) % n] * inverses[i];
+    }
+    q
+}
+
+// The quotient of f(X) = sum_j f_j X^j at x is sum_{i < d} h_i x^i with
+// h_i = sum_{j > i} f_j * [tau^(j - i - 1)]_1, so the proofs over the domain are the FFT of h.
+// h is a Toeplitz matrix-vector product, computed as the cyclic convolution of the coefficients
+// with the reversed powers [tau^(d - 1)]_1, ..., [tau^0]_1 over a domain twice as large.
+fn open_all<E: Pairing>(
+    powers: &[E::G1Affine],
+    polynomial: &DensePolynomial<E::ScalarField>,
+    domain_size: usize,
+) -> Result<Vec<E::G1>, SolvencyError> {
+    let domain = GeneralEvaluationDomain::<E::ScalarField>::new(domain_size)
+        .ok_or(SolvencyError::InvalidDomainSize(domain_size))?;
+    let n = domain.size();
+    let coeffs = &polynomial.coeffs;
+    if coeffs.len() > n {
+        return Err(SolvencyError::DegreeTooLarge {
+            degree: coeffs.len() - 1,
+            max_degree: n - 1,
+        });
+    }
+    check_degree::<E>(powers, coeffs)?;
+    // constant polynomials have zero quotients
+    if coeffs.len() < 2 {
+        return Ok(vec![E::G1::zero(); n]);
+    }
+    let d = coeffs.len() - 1;
+    let double_domain = GeneralEvaluationDomain::<E::ScalarField>::new(2 * n)
+        .ok_or(SolvencyError::InvalidDomainSize(2 * n))?;
+
+    let mut reversed_powers: Vec<E::G1> =
+        (0..d).map(|t| powers[d - 1 - t].into_group()).collect();
+    double_domain.fft_in_place(&mut reversed_powers);
+    let mut coeffs = coeffs.clone();
+    double_domain.fft_in_place(&mut coeffs);
+    for (point, coeff) in reversed_powers.iter_mut().zip(coeffs) {
+        *point *= coeff;
+    }
+    double_domain.ifft_in_place(&mut reversed_powers);
 
-        let mut py = self.g1 * E::ScalarField::ZERO;
-        for (i, coeff) in lagrange_polynomial.coeffs.iter().enumerate() {
-            py += self.crs[i] * coeff;
+    // the convolution is at most 2d long, so h_i is its (d + i)-th entry without wrap around
+    let mut h = reversed_powers[d..2 * d].to_vec();
+    h.resize(n, E::G1::zero());
+    domain.fft_in_place(&mut h);
+    Ok(h)
+}
+
+fn open_all_users<E: Pairing>(
+    powers: &[E::G1Affine],
+    polynomial: &DensePolynomial<E::ScalarField>,
+    domain_size: usize,
+    n_users: usize,
+) -> Result<Vec<E::G1>, SolvencyError> {
+    let proofs = open_all::<E>(powers, polynomial, domain_size)?;
+    if 2 * n_users > proofs.len() {
+        return Err(SolvencyError::LengthMismatch {
+            expected: proofs.len(),
+            found: 2 * n_users,
+        });
+    }
+    let domain = GeneralEvaluationDomain::<E::ScalarField>::new(domain_size)
+        .ok_or(SolvencyError::InvalidDomainSize(domain_size))?;
+    (0..n_users)
+        .map(|i| {
+            combine_openings::<E>(
+                &[domain.element(2 * i), domain.element(2 * i + 1)],
+                &proofs[2 * i..2 * i + 2],
+            )
+        })
+        .collect()
+}
+
+// e(pi, [tau - z]_2) == e(commitment - [y]_1, g2)
+fn check_opening<E: Pairing>(
+    g2: E::G2,
+    vk: E::G2,
+    py: E::G1,
+    z: E::ScalarField,
+    commitment: E::G1,
+    pi: E::G1,
+) -> bool {
+    let pz = g2 * z;
+    let lhs = E::pairing(pi, vk - pz);
+    let rhs = E::pairing(commitment - py, g2);
+    lhs == rhs
+}
+
+// The commitments and values are combined with the same powers of gamma as in batch_open, and
+// the result is checked as a single opening
+#[allow(clippy::too_many_arguments)]
+fn check_batch_opening<E: Pairing>(
+    g1: E::G1,
+    g2: E::G2,
+    vk: E::G2,
+    commitments: &[E::G1],
+    values: &[E::ScalarField],
+    z: E::ScalarField,
+    transcript: &mut Transcript,
+    pi: E::G1,
+) -> bool {
+    if commitments.len() != values.len() {
+        return false;
+    }
+    let gamma = batch_challenge::<E>(transcript, commitments, z, values);
+    let mut commitment = E::G1::zero();
+    let mut y = E::ScalarField::zero();
+    for (c, v) in commitments.iter().zip(values).rev() {
+        commitment = commitment * gamma + c;
+        y = y * gamma + v;
+    }
+    check_opening::<E>(g2, vk, g1 * y, z, commitment, pi)
+}
+
+// The verifier commits to L(X) from the commitments and the claimed values, see
+// shplonk_commitment, and checks the opening of F at z to 0: e(F + z * W', g2) == e(W', [tau]_2)
+#[allow(clippy::too_many_arguments)]
+fn check_multi_point_opening<E: Pairing>(
+    g1: E::G1,
+    g2: E::G2,
+    vk: E::G2,
+    commitments: &[E::G1],
+    point_sets: &[Vec<E::ScalarField>],
+    values: &[Vec<Evaluation<E>>],
+    transcript: &mut Transcript,
+    proof: &MultiPointProof<E>,
+) -> bool {
+    match shplonk_commitment::<E>(g1, commitments, point_sets, values, transcript, proof.w) {
+        Some((f, z)) => {
+            E::multi_pairing([f + proof.w_prime * z, -proof.w_prime], [g2, vk]).is_zero()
         }
+        None => false,
+    }
+}
 
-        let lhs = E::pairing(pi, pz);
-        let rhs = E::pairing(commitment - py, self.g2);
+// F = sum gamma^i / Z_i(z) * (C_i - r_i(z) * one) - W, with r_i(z) = sum_j lambda_ij(z) * y_ij,
+// where `one` commits to the constant polynomial 1, and the challenge z. The challenges are
+// replayed as in shplonk_open. None if the inputs are inconsistent.
+pub(crate) fn shplonk_commitment<E: Pairing>(
+    one: E::G1,
+    commitments: &[E::G1],
+    point_sets: &[Vec<E::ScalarField>],
+    values: &[Vec<Evaluation<E>>],
+    transcript: &mut Transcript,
+    w: E::G1,
+) -> Option<(E::G1, E::ScalarField)> {
+    if commitments.len() != point_sets.len() || commitments.len() != values.len() {
+        return None;
+    }
+    let encrypted: Vec<Vec<E::G1>> = values
+        .iter()
+        .map(|set| {
+            set.iter()
+                .map(|value| match value {
+                    Evaluation::Scalar(y) => one * y,
+                    Evaluation::Encrypted(py) => *py,
+                })
+                .collect()
+        })
+        .collect();
+    let gamma = shplonk_gamma::<E>(transcript, commitments, point_sets, &encrypted);
+    transcript.append(b"w", &w);
+    let z = transcript.challenge_scalar::<E::ScalarField>(b"z");
+    let mut points = vec![];
+    let mut scalars = vec![];
+    let mut y_sum = E::ScalarField::zero();
+    let mut gamma_i = E::ScalarField::ONE;
+    for ((commitment, opened_points), opened_values) in
+        commitments.iter().zip(point_sets).zip(values)
+    {
+        if opened_points.len() != opened_values.len() {
+            return None;
+        }
+        let lambdas = lagrange_coefficients::<E>(opened_points, z).ok()?;
+        let mut zero_at_z = E::ScalarField::ONE;
+        for x in opened_points {
+            zero_at_z *= z - x;
+        }
+        let scale = gamma_i * zero_at_z.inverse()?;
+        points.push(*commitment);
+        scalars.push(scale);
+        for (lambda, value) in lambdas.iter().zip(opened_values) {
+            match value {
+                Evaluation::Scalar(y) => y_sum += scale * lambda * y,
+                Evaluation::Encrypted(py) => {
+                    points.push(*py);
+                    scalars.push(-scale * lambda);
+                }
+            }
+        }
+        gamma_i *= gamma;
+    }
+    let points = E::G1::normalize_batch(&points);
+    Some((E::G1::msm_unchecked(&points, &scalars) - one * y_sum - w, z))
+}
+
+// e(pi, [Z(tau)]_2) == e(commitment - [L(tau)]_1, g2)
+fn check_multi_opening<E: Pairing>(
+    powers_of_g1: &[E::G1Affine],
+    powers_of_g2: &[E::G2Affine],
+    g2: E::G2,
+    commitment: E::G1,
+    pi: E::G1,
+    zero_polynomial: &DensePolynomial<E::ScalarField>,
+    lagrange_polynomial: &DensePolynomial<E::ScalarField>,
+) -> bool {
+    if zero_polynomial.coeffs.len() > powers_of_g2.len()
+        || lagrange_polynomial.coeffs.len() > powers_of_g1.len()
+    {
+        return false;
+    }
+    let pz = E::G2::msm_unchecked(powers_of_g2, &zero_polynomial.coeffs);
+    let py = E::G1::msm_unchecked(powers_of_g1, &lagrange_polynomial.coeffs);
 
-        lhs == rhs
+    let lhs = E::pairing(pi, pz);
+    let rhs = E::pairing(commitment - py, g2);
+
+    lhs == rhs
+}
+
+// Folds the claims with the powers r_i = r^i of a challenge r. Each claim checks
+// e(pi, [tau]_2) == e(C - [y]_1 + z * pi, g2), so all of them hold (up to a negligible
+// probability) iff e(sum r_i * pi_i, [tau]_2) * e(-sum r_i * (C_i - [y_i]_1 + z_i * pi_i), g2) == 1
+fn batch_check<E: Pairing>(
+    g1: E::G1,
+    g2: E::G2,
+    vk: E::G2,
+    claims: &[OpeningClaim<E>],
+    transcript: &mut Transcript,
+) -> bool {
+    if claims.is_empty() {
+        return true;
+    }
+    for claim in claims {
+        transcript.append(b"commitment", &claim.commitment);
+        transcript.append(b"z", &claim.z);
+        match claim.y {
+            Evaluation::Scalar(y) => transcript.append(b"y", &y),
+            Evaluation::Encrypted(py) => transcript.append(b"encrypted_y", &py),
+        }
+        transcript.append(b"pi", &claim.pi);
+    }
+    let challenge = transcript.challenge_scalar::<E::ScalarField>(b"r");
+    let mut r = E::ScalarField::ONE;
+    let mut points = Vec::with_capacity(3 * claims.len());
+    let mut scalars = Vec::with_capacity(3 * claims.len());
+    let mut proofs = Vec::with_capacity(claims.len());
+    let mut challenges = Vec::with_capacity(claims.len());
+    // the clear values are summed up in the field and lifted to G1 once
+    let mut y_sum = E::ScalarField::zero();
+    for claim in claims {
+        points.push(claim.commitment);
+        scalars.push(r);
+        points.push(claim.pi);
+        scalars.push(r * claim.z);
+        match claim.y {
+            Evaluation::Scalar(y) => y_sum += r * y,
+            Evaluation::Encrypted(py) => {
+                points.push(py);
+                scalars.push(-r);
+            }
+        }
+        proofs.push(claim.pi);
+        challenges.push(r);
+        r *= challenge;
+    }
+    let points = E::G1::normalize_batch(&points);
+    let proofs = E::G1::normalize_batch(&proofs);
+    let lhs = E::G1::msm_unchecked(&proofs, &challenges);
+    let rhs = E::G1::msm_unchecked(&points, &scalars) - g1 * y_sum;
+    E::multi_pairing([lhs, -rhs], [vk, g2]).is_zero()
+}
+
+// Bisects the claims until the failing ones are isolated. `offset` is the index of the
+// first claim in the original slice.
+fn batch_failures<E: Pairing>(
+    g1: E::G1,
+    g2: E::G2,
+    vk: E::G2,
+    claims: &[OpeningClaim<E>],
+    offset: usize,
+    transcript: &mut Transcript,
+) -> Vec<usize> {
+    if batch_check::<E>(g1, g2, vk, claims, transcript) {
+        return vec![];
+    }
+    if claims.len() == 1 {
+        return vec![offset];
     }
+    let (left, right) = claims.split_at(claims.len() / 2);
+    let mut failures = batch_failures::<E>(g1, g2, vk, left, offset, transcript);
+    failures.extend(batch_failures::<E>(g1, g2, vk, right, offset + left.len(), transcript));
+    failures
 }
@@ -1,13 +1,18 @@
-use ark_bn254::Fr;
+use ark_ec::pairing::Pairing;
 use ark_poly::{
     univariate::DensePolynomial, EvaluationDomain, Evaluations, GeneralEvaluationDomain,
 };
 
-pub fn lagrange_interpolate(evals: &Vec<Fr>) -> DensePolynomial<Fr> {
-    // assert that the number of evaluations is a power of two
-    let k = evals.len() as usize;
-    let omegas = GeneralEvaluationDomain::<Fr>::new(k).unwrap();
-    let lagrange: DensePolynomial<Fr> =
-        Evaluations::<Fr>::from_vec_and_domain(evals.to_vec(), omegas).interpolate();
-    lagrange
+use crate::error::SolvencyError;
+
+pub fn lagrange_interpolate<E: Pairing>(
+    evals: &[E::ScalarField],
+) -> Result<DensePolynomial<E::ScalarField>, SolvencyError> {
+    // the evaluations are padded with zeroes up to the next power of two
+    let k = evals.len();
+    let omegas = GeneralEvaluationDomain::<E::ScalarField>::new(k)
+        .ok_or(SolvencyError::InvalidDomainSize(k))?;
+    let lagrange: DensePolynomial<E::ScalarField> =
+        Evaluations::<E::ScalarField>::from_vec_and_domain(evals.to_vec(), omegas).interpolate();
+    Ok(lagrange)
 }
@@ -1,20 +1,50 @@
+pub mod ceremony;
+pub mod constraints;
+pub mod error;
+pub mod ipa;
 pub mod kzg;
 pub mod lagrange;
+pub mod pcs;
 pub mod prover;
+pub mod ptau;
+pub mod serialization;
+pub mod transcript;
 pub mod utils;
 pub mod misc;
 
 #[cfg(test)]
+#[allow(non_snake_case)]
 mod tests {
-    use crate::prover::User;
+    use crate::ceremony::{
+        contribute_file, verify_contribution, verify_contribution_file, verify_transcript,
+        verify_transcript_files, Contribution, Powers,
+    };
+    use crate::constraints::{ConstraintProof, ConstraintSystem};
+    use crate::error::SolvencyError;
+    use crate::ipa::IPA;
+    use crate::kzg::{
+        Evaluation, KzgProver, KzgVerifier, MultiPointProof, OpeningClaim, ProverKey, VerifierKey,
+    };
+    use crate::pcs::PolynomialCommitmentScheme;
+    use crate::prover::{average_balance, User};
+    use crate::ptau::{load_ptau, read_ptau};
+    use crate::serialization::{from_bytes, from_json, to_bytes, to_json, CurveId, ObjectKind};
+    use crate::transcript::Transcript;
     use crate::utils::build_zero_polynomial;
     use crate::utils::compute_evaluations_for_specific_omegas;
     use crate::utils::generate_random_scalar_field_elements;
     use crate::utils::get_omega_domain;
+    use crate::utils::interpolate_points;
+    use crate::utils::{divide_by_zero_polynomial, evaluate_points, lagrange_coefficients};
 
     use super::*;
-    use ark_bn254::{Bn254, Fr as F, G1Projective as G1, G2Projective as G2};
+    use ark_bls12_381::Bls12_381;
+    use ark_bn254::{Bn254, Fq, Fr as F, G1Projective as G1, G2Projective as G2};
+    use ark_ec::pairing::Pairing;
+    use ark_ec::Group;
+    use ark_ff::PrimeField;
     use ark_poly::polynomial::univariate::DensePolynomial;
+    use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Compress};
     use ark_poly::DenseUVPolynomial;
     use ark_poly::EvaluationDomain;
     use ark_poly::Evaluations;
@@ -23,30 +53,89 @@ mod tests {
     use ark_std::rand::Rng;
     use ark_std::UniformRand;
     use ark_std::{test_rng, Zero};
+    use std::io::Cursor;
     use kzg::KZG;
     use lagrange::lagrange_interpolate;
-    use prover::generate_witness;
+    use prover::{
+        diff_witness, generate_witness, total_balance, verify_balance_cells, WitnessLayout,
+    };
 
     #[test]
     fn test_kzg_bn254() {
         let mut rng = test_rng();
         let degree = 10;
         let polynomial: DensePolynomial<F> = DenseUVPolynomial::rand(degree, &mut rng);
-        let tau = F::rand(&mut rng);
         let random_z = F::rand(&mut rng);
         let y = polynomial.evaluate(&random_z);
         let g1 = G1::rand(&mut rng);
         let g2 = G2::rand(&mut rng);
 
-        let mut kzg_bn254 = KZG::<Bn254>::new(g1, g2, degree);
-
-        let _ = kzg_bn254.setup(tau); // setup modifies in place the struct crs
-        let commitment = kzg_bn254.commit(&polynomial);
-        let opening = kzg_bn254.open(&polynomial, random_z, y);
+        let kzg_bn254 = KZG::<Bn254>::insecure_setup_for_testing(g1, g2, degree, &mut rng);
+        let commitment = kzg_bn254.commit(&polynomial).unwrap();
+        let opening = kzg_bn254.open(&polynomial, random_z, y).unwrap();
         let verify = kzg_bn254.verify(y, random_z, commitment, opening);
         assert!(verify);
     }
 
+    #[test]
+    fn test_msm_matches_serial_sum() {
+        let mut rng = test_rng();
+        let degree = 31;
+        let polynomial: DensePolynomial<F> = DenseUVPolynomial::rand(degree, &mut rng);
+        let kzg_bn254 = KZG::<Bn254>::insecure_setup_for_testing(
+            G1::rand(&mut rng),
+            G2::rand(&mut rng),
+            degree,
+            &mut rng,
+        );
+
+        // commitment as it used to be computed, one scalar multiplication at a time
+        let mut expected = G1::zero();
+        for (point, coeff) in kzg_bn254.crs.iter().zip(polynomial.coeffs.iter()) {
+            expected += *point * coeff;
+        }
+        assert_eq!(kzg_bn254.commit(&polynomial).unwrap(), expected);
+    }
+
+    #[test]
+    fn commit_degree_bounds() {
+        let mut rng = test_rng();
+        let degree = 8;
+        let kzg_bn254 = KZG::<Bn254>::insecure_setup_for_testing(
+            G1::rand(&mut rng),
+            G2::rand(&mut rng),
+            degree,
+            &mut rng,
+        );
+
+        // lower degree polynomials, including the zero polynomial, commit fine
+        let zero = DensePolynomial::<F>::zero();
+        assert_eq!(kzg_bn254.commit(&zero).unwrap(), G1::zero());
+        let small: DensePolynomial<F> = DenseUVPolynomial::rand(3, &mut rng);
+        let z = F::rand(&mut rng);
+        let y = small.evaluate(&z);
+        let commitment = kzg_bn254.commit(&small).unwrap();
+        let opening = kzg_bn254.open(&small, z, y).unwrap();
+        assert!(kzg_bn254.verify(y, z, commitment, opening));
+
+        // a polynomial of higher degree than the SRS is rejected
+        let large: DensePolynomial<F> = DenseUVPolynomial::rand(degree + 1, &mut rng);
+        assert_eq!(
+            kzg_bn254.commit(&large),
+            Err(SolvencyError::DegreeTooLarge {
+                degree: degree + 1,
+                max_degree: degree
+            })
+        );
+        assert_eq!(
+            kzg_bn254.open(&large, z, large.evaluate(&z)),
+            Err(SolvencyError::DegreeTooLarge {
+                degree: degree + 1,
+                max_degree: degree
+            })
+        );
+    }
+
     #[test]
     fn multi_opening() {
         let mut rng = test_rng();
@@ -56,31 +145,38 @@ mod tests {
         let n_leaves = n_users * 2; // user consists of (h(username), leaf)
 
         // p_omegas consists in the domain of P(X). we also store them in domain_elements
-        let (p_omegas, domain_elements) = get_omega_domain::<Bn254>(n_leaves);
+        let (p_omegas, domain_elements) = get_omega_domain::<Bn254>(n_leaves).unwrap();
 
         // P(omega^i) = random * i
         let p_evaluations = generate_random_scalar_field_elements::<Bn254>(&mut rng, n_leaves);
         let P: DensePolynomial<F> =
             Evaluations::<F>::from_vec_and_domain(p_evaluations.clone(), p_omegas).interpolate();
 
-        let mut kzg_bn254 = KZG::<Bn254>::new(G1::rand(&mut rng), G2::rand(&mut rng), n_leaves - 1);
-        let _ = kzg_bn254.setup(F::rand(&mut rng));
-        let commitment = kzg_bn254.commit(&P);
+        let kzg_bn254 = KZG::<Bn254>::insecure_setup_for_testing(
+            G1::rand(&mut rng),
+            G2::rand(&mut rng),
+            n_leaves - 1,
+            &mut rng,
+        );
+        let commitment = kzg_bn254.commit(&P).unwrap();
 
         // Build polynomial L(X), that consists into the "opening" of (username, balance)
         // we want the L(X) interpolated polynomial to be defined over the same domain of P
         // When evaluated at omega^2 and omega^3, it will be equal to P(omega^2) and P(omega^3)
-        let l_omegas = p_omegas.clone();
+        let l_omegas = p_omegas;
         let l_evaluations =
             compute_evaluations_for_specific_omegas::<Bn254>(vec![2, 3], &domain_elements, &P);
         let L: DensePolynomial<F> =
             Evaluations::<F>::from_vec_and_domain(l_evaluations.clone(), l_omegas).interpolate();
 
         // Build denominator polynomial Z(X) in [(P(x) - Q(X)) / Z(X)]
-        let Z = build_zero_polynomial::<Bn254>(&vec![domain_elements[2], domain_elements[3]]);
+        let Z =
+            build_zero_polynomial::<Bn254>(&[domain_elements[2], domain_elements[3]]).unwrap();
 
         // Perform multi opening, z is a vector of points at which we want to prove an opening for specific values
-        let pi = kzg_bn254.multi_open(&P, &L, vec![domain_elements[2], domain_elements[3]]);
+        let pi = kzg_bn254
+            .multi_open(&P, &L, vec![domain_elements[2], domain_elements[3]])
+            .unwrap();
         let verify = kzg_bn254.verify_multi_open(commitment, pi, &Z, &L);
         assert!(verify);
 
@@ -88,6 +184,909 @@ mod tests {
         assert!(!verify_wrong);
     }
 
+    #[test]
+    fn trimmed_keys() {
+        let mut rng = test_rng();
+        let n_leaves = 16;
+        let (_, domain_elements) = get_omega_domain::<Bn254>(n_leaves).unwrap();
+        let p_evaluations = generate_random_scalar_field_elements::<Bn254>(&mut rng, n_leaves);
+        let P = lagrange_interpolate::<Bn254>(&p_evaluations).unwrap();
+
+        let kzg_bn254 = KZG::<Bn254>::insecure_setup_for_testing(
+            G1::rand(&mut rng),
+            G2::rand(&mut rng),
+            n_leaves,
+            &mut rng,
+        );
+        let (pk, vk) = kzg_bn254.trim(n_leaves - 1, 2).unwrap();
+        assert_eq!(pk.max_degree(), Ok(n_leaves - 1));
+        assert_eq!(vk.max_points(), Ok(2));
+        assert!(kzg_bn254.trim(n_leaves + 1, 2).is_err());
+
+        let commitment = pk.commit(&P).unwrap();
+        assert_eq!(commitment, kzg_bn254.commit(&P).unwrap());
+
+        // single opening
+        let z = F::rand(&mut rng);
+        let y = P.evaluate(&z);
+        let pi = pk.open(&P, z, y).unwrap();
+        assert!(vk.verify(y, z, commitment, pi));
+        assert!(!vk.verify(y + F::from(1), z, commitment, pi));
+
+        // multi-opening at (omega^2, omega^3) with the lowest degree interpolant
+        let points = vec![domain_elements[2], domain_elements[3]];
+        let L = interpolate_points::<Bn254>(&points, &[p_evaluations[2], p_evaluations[3]]).unwrap();
+        let Z = build_zero_polynomial::<Bn254>(&points).unwrap();
+        let pi = pk.multi_open(&P, &L, points.clone()).unwrap();
+        assert!(vk.verify_multi_open(commitment, pi, &Z, &L));
+
+        // three points do not fit in a verifier key trimmed to two
+        let points = vec![domain_elements[2], domain_elements[3], domain_elements[4]];
+        let values = [p_evaluations[2], p_evaluations[3], p_evaluations[4]];
+        let L = interpolate_points::<Bn254>(&points, &values).unwrap();
+        let Z = build_zero_polynomial::<Bn254>(&points).unwrap();
+        let pi = pk.multi_open(&P, &L, points).unwrap();
+        assert!(kzg_bn254.verify_multi_open(commitment, pi, &Z, &L));
+        assert!(!vk.verify_multi_open(commitment, pi, &Z, &L));
+
+        // keys deserialized without any power are rejected instead of underflowing
+        let mut bytes = vec![];
+        ProverKey::<Bn254> {
+            powers_of_g1: vec![],
+        }
+        .serialize_compressed(&mut bytes)
+        .unwrap();
+        let empty = ProverKey::<Bn254>::deserialize_compressed(&bytes[..]).unwrap();
+        assert!(matches!(empty.max_degree(), Err(SolvencyError::InvalidSrs(_))));
+        assert!(matches!(empty.commit(&P), Err(SolvencyError::InvalidSrs(_))));
+        assert!(matches!(empty.lagrange_key(16), Err(SolvencyError::InvalidSrs(_))));
+        let mut empty_vk = vk.clone();
+        empty_vk.powers_of_g2.clear();
+        assert!(matches!(empty_vk.max_points(), Err(SolvencyError::InvalidSrs(_))));
+    }
+
+    #[test]
+    fn transcript_challenges() {
+        let mut rng = test_rng();
+        let point = G1::rand(&mut rng);
+        let scalar = F::rand(&mut rng);
+        let mut transcript = Transcript::new(b"test");
+        transcript.append(b"point", &point);
+        transcript.append(b"scalar", &scalar);
+        let mut replayed = transcript.clone();
+
+        // the prover and the verifier squeeze the same challenges, which differ from each other
+        let first: F = transcript.challenge_scalar(b"first");
+        assert_eq!(first, replayed.challenge_scalar::<F>(b"first"));
+        let second: F = transcript.challenge_scalar(b"first");
+        assert_eq!(second, replayed.challenge_scalar::<F>(b"first"));
+        assert_ne!(first, second);
+
+        // challenges depend on the protocol, the messages, their labels and their order
+        let challenge = |transcript: &mut Transcript| transcript.challenge_scalar::<F>(b"first");
+        let mut other = Transcript::new(b"other");
+        other.append(b"point", &point);
+        other.append(b"scalar", &scalar);
+        assert_ne!(challenge(&mut other), first);
+        let mut other = Transcript::new(b"test");
+        other.append(b"point", &point);
+        other.append(b"scalar", &(scalar + F::from(1)));
+        assert_ne!(challenge(&mut other), first);
+        let mut other = Transcript::new(b"test");
+        other.append(b"point", &point);
+        other.append(b"other", &scalar);
+        assert_ne!(challenge(&mut other), first);
+        let mut other = Transcript::new(b"test");
+        other.append(b"scalar", &scalar);
+        other.append(b"point", &point);
+        assert_ne!(challenge(&mut other), first);
+        let mut other = Transcript::new(b"test");
+        other.append(b"point", &point);
+        other.append(b"scalar", &scalar);
+        assert_eq!(challenge(&mut other), first);
+    }
+
+    #[test]
+    fn batch_verification() {
+        let mut rng = test_rng();
+        let degree = 15;
+        let kzg_bn254 = KZG::<Bn254>::insecure_setup_for_testing(
+            G1::rand(&mut rng),
+            G2::rand(&mut rng),
+            degree,
+            &mut rng,
+        );
+        let (_, vk) = kzg_bn254.trim(degree, 1).unwrap();
+        assert!(vk.batch_verify(&[], &mut Transcript::new(b"test")));
+
+        // openings of two polynomials, with evaluations given in the clear and in G1
+        let polynomials = [
+            DensePolynomial::<F>::rand(degree, &mut rng),
+            DensePolynomial::<F>::rand(degree, &mut rng),
+        ];
+        let mut claims = vec![];
+        for i in 0..10 {
+            let polynomial = &polynomials[i % 2];
+            let commitment = kzg_bn254.commit(polynomial).unwrap();
+            let z = F::rand(&mut rng);
+            let y = polynomial.evaluate(&z);
+            let pi = kzg_bn254.open(polynomial, z, y).unwrap();
+            let y = if i % 3 == 0 {
+                Evaluation::Encrypted(kzg_bn254.g1 * y)
+            } else {
+                Evaluation::Scalar(y)
+            };
+            claims.push(OpeningClaim { commitment, z, y, pi });
+        }
+        assert!(vk.batch_verify(&claims, &mut Transcript::new(b"test")));
+        assert!(kzg_bn254.batch_verify(&claims, &mut Transcript::new(b"test")));
+        assert!(vk.batch_verify_failures(&claims, &mut Transcript::new(b"test")).is_empty());
+
+        // tamper with a clear evaluation, an encrypted one and a proof
+        claims[1].y = Evaluation::Scalar(F::from(7));
+        claims[6].y = Evaluation::Encrypted(kzg_bn254.g1);
+        claims[8].pi *= F::from(2);
+        let mut transcript = Transcript::new(b"test");
+        assert!(!vk.batch_verify(&claims, &mut transcript));
+        assert_eq!(vk.batch_verify_failures(&claims, &mut transcript), vec![1, 6, 8]);
+        assert_eq!(
+            kzg_bn254.batch_verify_failures(&claims, &mut Transcript::new(b"test")),
+            vec![1, 6, 8]
+        );
+    }
+
+    #[test]
+    fn batch_opening() {
+        let mut rng = test_rng();
+        let degree = 15;
+        let kzg_bn254 = KZG::<Bn254>::insecure_setup_for_testing(
+            G1::rand(&mut rng),
+            G2::rand(&mut rng),
+            degree,
+            &mut rng,
+        );
+        let (pk, vk) = kzg_bn254.trim(degree, 1).unwrap();
+
+        // three columns of different degrees opened at the same point
+        let polynomials: Vec<DensePolynomial<F>> = [degree, degree - 1, 3]
+            .iter()
+            .map(|d| DensePolynomial::rand(*d, &mut rng))
+            .collect();
+        let references: Vec<&DensePolynomial<F>> = polynomials.iter().collect();
+        let commitments: Vec<G1> = polynomials
+            .iter()
+            .map(|polynomial| kzg_bn254.commit(polynomial).unwrap())
+            .collect();
+        let z = F::rand(&mut rng);
+        let mut values: Vec<F> = polynomials.iter().map(|p| p.evaluate(&z)).collect();
+
+        let pi = kzg_bn254
+            .batch_open(&references, &commitments, z, &mut Transcript::new(b"test"))
+            .unwrap();
+        assert_eq!(
+            pi,
+            pk.batch_open(&references, &commitments, z, &mut Transcript::new(b"test"))
+                .unwrap()
+        );
+        let mut transcript = Transcript::new(b"test");
+        assert!(kzg_bn254.verify_batch_open(&commitments, &values, z, &mut transcript, pi));
+        let mut transcript = Transcript::new(b"test");
+        assert!(vk.verify_batch_open(&commitments, &values, z, &mut transcript, pi));
+
+        // a batch of one is checked with the first power of gamma, i.e. as a single opening
+        let pi_single = kzg_bn254.open(&polynomials[0], z, values[0]).unwrap();
+        let single = &commitments[..1];
+        let mut transcript = Transcript::new(b"test");
+        assert!(vk.verify_batch_open(single, &values[..1], z, &mut transcript, pi_single));
+
+        // the challenge depends on the commitments, which are absorbed by the verifier itself
+        let mut swapped = commitments.clone();
+        swapped.swap(0, 1);
+        let mut swapped_values = values.clone();
+        swapped_values.swap(0, 1);
+        let mut transcript = Transcript::new(b"test");
+        assert!(!vk.verify_batch_open(&swapped, &swapped_values, z, &mut transcript, pi));
+        let mut transcript = Transcript::new(b"other");
+        assert!(!vk.verify_batch_open(&commitments, &values, z, &mut transcript, pi));
+        let mut transcript = Transcript::new(b"test");
+        assert!(!vk.verify_batch_open(&commitments, &values[..2], z, &mut transcript, pi));
+        values[2] += F::from(1);
+        let mut transcript = Transcript::new(b"test");
+        assert!(!vk.verify_batch_open(&commitments, &values, z, &mut transcript, pi));
+
+        let mut transcript = Transcript::new(b"test");
+        assert_eq!(
+            kzg_bn254.batch_open(&references, &commitments[..2], z, &mut transcript),
+            Err(SolvencyError::LengthMismatch {
+                expected: 3,
+                found: 2
+            })
+        );
+    }
+
+    #[test]
+    fn multi_point_opening() {
+        let mut rng = test_rng();
+        let degree = 31;
+        let kzg_bn254 = KZG::<Bn254>::insecure_setup_for_testing(
+            G1::rand(&mut rng),
+            G2::rand(&mut rng),
+            degree,
+            &mut rng,
+        );
+        let (pk, vk) = kzg_bn254.trim(degree, 1).unwrap();
+        let (_, domain_elements) = get_omega_domain::<Bn254>(degree + 1).unwrap();
+
+        // two polynomials opened over disjoint rows, a third over a row shared with the first
+        let polynomials: Vec<DensePolynomial<F>> = (0..3)
+            .map(|_| DensePolynomial::rand(degree, &mut rng))
+            .collect();
+        let references: Vec<&DensePolynomial<F>> = polynomials.iter().collect();
+        let commitments: Vec<G1> = polynomials
+            .iter()
+            .map(|polynomial| kzg_bn254.commit(polynomial).unwrap())
+            .collect();
+        let point_sets = vec![
+            vec![domain_elements[1], domain_elements[5], domain_elements[9]],
+            vec![domain_elements[2], domain_elements[30]],
+            vec![domain_elements[5]],
+        ];
+        // the second polynomial's values are given in G1
+        let mut values: Vec<Vec<Evaluation<Bn254>>> = polynomials
+            .iter()
+            .zip(&point_sets)
+            .enumerate()
+            .map(|(i, (polynomial, points))| {
+                points
+                    .iter()
+                    .map(|x| {
+                        let y = polynomial.evaluate(x);
+                        if i == 1 {
+                            Evaluation::Encrypted(kzg_bn254.g1 * y)
+                        } else {
+                            Evaluation::Scalar(y)
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let proof = kzg_bn254
+            .multi_point_open(&references, &commitments, &point_sets, &mut Transcript::new(b"test"))
+            .unwrap();
+        assert_eq!(
+            proof,
+            pk.multi_point_open(
+                &references,
+                &commitments,
+                &point_sets,
+                &mut Transcript::new(b"test")
+            )
+            .unwrap()
+        );
+        assert!(kzg_bn254.verify_multi_point_open(
+            &commitments,
+            &point_sets,
+            &values,
+            &mut Transcript::new(b"test"),
+            &proof,
+        ));
+        let check = |point_sets: &[Vec<F>],
+                     values: &[Vec<Evaluation<Bn254>>],
+                     transcript: &mut Transcript,
+                     proof: &MultiPointProof<Bn254>| {
+            vk.verify_multi_point_open(&commitments, point_sets, values, transcript, proof)
+        };
+        assert!(check(&point_sets, &values, &mut Transcript::new(b"test"), &proof));
+
+        // other transcripts, wrong proofs, wrong commitments, wrong points and wrong values are
+        // rejected
+        assert!(!check(&point_sets, &values, &mut Transcript::new(b"other"), &proof));
+        let mut wrong_proof = proof;
+        wrong_proof.w += kzg_bn254.g1;
+        let mut transcript = Transcript::new(b"test");
+        assert!(!check(&point_sets, &values, &mut transcript, &wrong_proof));
+        let mut wrong_commitments = commitments.clone();
+        wrong_commitments[0] += kzg_bn254.g1;
+        assert!(!vk.verify_multi_point_open(
+            &wrong_commitments,
+            &point_sets,
+            &values,
+            &mut Transcript::new(b"test"),
+            &proof,
+        ));
+        let mut wrong_points = point_sets.clone();
+        wrong_points[2][0] = domain_elements[6];
+        let mut transcript = Transcript::new(b"test");
+        assert!(!check(&wrong_points, &values, &mut transcript, &proof));
+        assert!(!vk.verify_multi_point_open(
+            &commitments[..2],
+            &point_sets,
+            &values,
+            &mut Transcript::new(b"test"),
+            &proof,
+        ));
+        values[1][1] = Evaluation::Encrypted(kzg_bn254.g1);
+        let mut transcript = Transcript::new(b"test");
+        assert!(!check(&point_sets, &values, &mut transcript, &proof));
+
+        let mut transcript = Transcript::new(b"test");
+        assert_eq!(
+            kzg_bn254.multi_point_open(
+                &references,
+                &commitments,
+                &point_sets[..2],
+                &mut transcript
+            ),
+            Err(SolvencyError::LengthMismatch {
+                expected: 3,
+                found: 2
+            })
+        );
+        assert_eq!(
+            kzg_bn254.multi_point_open(
+                &references,
+                &commitments[..2],
+                &point_sets,
+                &mut Transcript::new(b"test")
+            ),
+            Err(SolvencyError::LengthMismatch {
+                expected: 3,
+                found: 2
+            })
+        );
+    }
+
+    #[test]
+    fn open_all_points() {
+        let mut rng = test_rng();
+        let kzg_bn254 = KZG::<Bn254>::insecure_setup_for_testing(
+            G1::rand(&mut rng),
+            G2::rand(&mut rng),
+            31,
+            &mut rng,
+        );
+
+        // a polynomial filling its domain, and a smaller one over a larger domain
+        for (degree, domain_size) in [(15, 16), (20, 32)] {
+            let P = DensePolynomial::<F>::rand(degree, &mut rng);
+            let (_, domain_elements) = get_omega_domain::<Bn254>(domain_size).unwrap();
+            let proofs = kzg_bn254.open_all(&P, domain_size).unwrap();
+            assert_eq!(proofs.len(), domain_size);
+            for (x, pi) in domain_elements.iter().zip(&proofs) {
+                assert_eq!(*pi, kzg_bn254.open(&P, *x, P.evaluate(x)).unwrap());
+            }
+
+            // the proofs of rows (2i, 2i + 1) are multi-openings over both rows
+            let commitment = kzg_bn254.commit(&P).unwrap();
+            let user_proofs = kzg_bn254.open_all_users(&P, domain_size, 5).unwrap();
+            assert_eq!(user_proofs.len(), 5);
+            for (i, pi) in user_proofs.iter().enumerate() {
+                let points = vec![domain_elements[2 * i], domain_elements[2 * i + 1]];
+                let values = [P.evaluate(&points[0]), P.evaluate(&points[1])];
+                let L = interpolate_points::<Bn254>(&points, &values).unwrap();
+                let Z = build_zero_polynomial::<Bn254>(&points).unwrap();
+                assert_eq!(*pi, kzg_bn254.multi_open(&P, &L, points).unwrap());
+                assert!(kzg_bn254.verify_multi_open(commitment, *pi, &Z, &L));
+            }
+        }
+
+        let P = DensePolynomial::<F>::rand(16, &mut rng);
+        assert!(kzg_bn254.open_all(&P, 16).is_err());
+        assert!(kzg_bn254.open_all_users(&P, 32, 17).is_err());
+        let constant = DensePolynomial::from_coefficients_vec(vec![F::from(3)]);
+        assert!(kzg_bn254
+            .open_all(&constant, 8)
+            .unwrap()
+            .iter()
+            .all(|pi| pi.is_zero()));
+    }
+
+    #[test]
+    fn interpolation_over_points_and_cosets() {
+        let mut rng = test_rng();
+        let (_, domain_elements) = get_omega_domain::<Bn254>(64).unwrap();
+        let P = DensePolynomial::<F>::rand(63, &mut rng);
+        let z = F::rand(&mut rng);
+
+        // arbitrary points, every fourth row from row 3 (a coset of the subgroup of size 16), and
+        // the same rows out of order
+        let arbitrary: Vec<F> = (0..13).map(|_| F::rand(&mut rng)).collect();
+        let coset: Vec<F> = (0..16).map(|i| domain_elements[4 * i + 3]).collect();
+        let mut shuffled = coset.clone();
+        shuffled.swap(0, 5);
+        for points in [arbitrary, coset, shuffled] {
+            let values: Vec<F> = points.iter().map(|x| P.evaluate(x)).collect();
+            assert_eq!(evaluate_points::<Bn254>(&P, &points), values);
+            let L = interpolate_points::<Bn254>(&points, &values).unwrap();
+            assert!(L.degree() < points.len());
+            assert!(points.iter().zip(&values).all(|(x, y)| L.evaluate(x) == *y));
+
+            let Z = build_zero_polynomial::<Bn254>(&points).unwrap();
+            assert_eq!(Z.degree(), points.len());
+            assert_eq!(Z.coeffs.last(), Some(&F::from(1)));
+            assert!(points.iter().all(|x| Z.evaluate(x).is_zero()));
+            let quotient = divide_by_zero_polynomial::<Bn254>(&(&P - &L), &Z);
+            assert_eq!(quotient, &(&P - &L) / &Z);
+            assert_eq!(&quotient * &Z, &P - &L);
+
+            let coefficients = lagrange_coefficients::<Bn254>(&points, z).unwrap();
+            let at_z: F = coefficients.iter().zip(&values).map(|(c, y)| *c * y).sum();
+            assert_eq!(at_z, L.evaluate(&z));
+        }
+
+        let points = [domain_elements[1], domain_elements[1]];
+        assert_eq!(
+            interpolate_points::<Bn254>(&points, &[F::from(1), F::from(1)]),
+            Err(SolvencyError::DuplicatePoints)
+        );
+    }
+
+    #[test]
+    fn lagrange_basis_commitments() {
+        let mut rng = test_rng();
+        let n = 16;
+        let kzg_bn254 = KZG::<Bn254>::insecure_setup_for_testing(
+            G1::rand(&mut rng),
+            G2::rand(&mut rng),
+            n - 1,
+            &mut rng,
+        );
+        let (pk, _) = kzg_bn254.trim(n - 1, 1).unwrap();
+        let lagrange_key = kzg_bn254.lagrange_key(n).unwrap();
+        assert_eq!(lagrange_key, pk.lagrange_key(n).unwrap());
+        assert_eq!(lagrange_key.domain_size(), n);
+        assert!(kzg_bn254.lagrange_key(2 * n).is_err());
+
+        // missing evaluations are zeroes, as when interpolating
+        let evaluations = generate_random_scalar_field_elements::<Bn254>(&mut rng, n - 3);
+        let P = lagrange_interpolate::<Bn254>(&evaluations).unwrap();
+        let commitment = lagrange_key.commit_evaluations(&evaluations).unwrap();
+        assert_eq!(commitment, kzg_bn254.commit(&P).unwrap());
+
+        let (_, domain_elements) = get_omega_domain::<Bn254>(n).unwrap();
+        for k in [0, 5, n - 1] {
+            let pi = lagrange_key.open_evaluations(&evaluations, k).unwrap();
+            let x = domain_elements[k];
+            assert_eq!(pi, kzg_bn254.open(&P, x, P.evaluate(&x)).unwrap());
+            assert!(kzg_bn254.verify(P.evaluate(&x), x, commitment, pi));
+        }
+
+        let indexes = [2, 3, 9];
+        let points: Vec<F> = indexes.iter().map(|k| domain_elements[*k]).collect();
+        let values: Vec<F> = points.iter().map(|x| P.evaluate(x)).collect();
+        let L = interpolate_points::<Bn254>(&points, &values).unwrap();
+        let Z = build_zero_polynomial::<Bn254>(&points).unwrap();
+        let pi = lagrange_key
+            .multi_open_evaluations(&evaluations, &indexes)
+            .unwrap();
+        assert_eq!(pi, kzg_bn254.multi_open(&P, &L, points).unwrap());
+        assert!(kzg_bn254.verify_multi_open(commitment, pi, &Z, &L));
+
+        assert_eq!(
+            lagrange_key.open_evaluations(&evaluations, n),
+            Err(SolvencyError::IndexOutOfRange { index: n, size: n })
+        );
+        assert_eq!(
+            lagrange_key.multi_open_evaluations(&evaluations, &[1, 1]),
+            Err(SolvencyError::DuplicatePoints)
+        );
+        assert!(lagrange_key.commit_evaluations(&vec![F::from(1); n + 1]).is_err());
+    }
+
+    #[test]
+    fn incremental_updates() {
+        let mut rng = test_rng();
+        let users: Vec<User> = (0..4)
+            .map(|i| User {
+                username: 100 + i,
+                balance: 1000 * (i as u64 + 1),
+                salt: rng.gen(),
+            })
+            .collect();
+        let mut new_users = users.clone();
+        new_users[1].balance = 1234;
+
+        let layout = WitnessLayout::default();
+        let (total, new_total) = (total_balance(&users), total_balance(&new_users));
+        let (p_witness, i_witness) =
+            generate_witness::<Bn254>(users.clone(), total, &layout).unwrap();
+        let (new_p_witness, new_i_witness) =
+            generate_witness::<Bn254>(new_users.clone(), new_total, &layout).unwrap();
+        let (p_changes, i_changes) =
+            diff_witness::<Bn254>(users, total, new_users, new_total, &layout).unwrap();
+        // only the balance row of P(X) changes
+        assert_eq!(p_changes, vec![(3, F::from(1234) - F::from(2000))]);
+        assert!(!i_changes.is_empty());
+
+        let n = p_witness.len();
+        let kzg_bn254 = KZG::<Bn254>::insecure_setup_for_testing(
+            G1::rand(&mut rng),
+            G2::rand(&mut rng),
+            n - 1,
+            &mut rng,
+        );
+        let lagrange_key = kzg_bn254.lagrange_key(n).unwrap();
+        for (witness, new_witness, changes) in [
+            (&p_witness, &new_p_witness, &p_changes),
+            (&i_witness, &new_i_witness, &i_changes),
+        ] {
+            let commitment = lagrange_key.commit_evaluations(witness).unwrap();
+            assert_eq!(
+                lagrange_key.update_commitment(commitment, changes).unwrap(),
+                lagrange_key.commit_evaluations(new_witness).unwrap()
+            );
+
+            // cached proofs of changed and unchanged rows
+            for index in [0, 3, 15] {
+                let proof = lagrange_key.open_evaluations(witness, index).unwrap();
+                assert_eq!(
+                    lagrange_key.update_opening(proof, index, changes).unwrap(),
+                    lagrange_key.open_evaluations(new_witness, index).unwrap()
+                );
+            }
+        }
+
+        let P = lagrange_interpolate::<Bn254>(&p_witness).unwrap();
+        let new_P = lagrange_interpolate::<Bn254>(&new_p_witness).unwrap();
+        let user_proofs = kzg_bn254.open_all_users(&P, n, 4).unwrap();
+        assert_eq!(
+            lagrange_key
+                .update_user_proofs(&user_proofs, &p_changes)
+                .unwrap(),
+            kzg_bn254.open_all_users(&new_P, n, 4).unwrap()
+        );
+
+        assert_eq!(
+            lagrange_key.update_commitment(G1::zero(), &[(n, F::from(1))]),
+            Err(SolvencyError::IndexOutOfRange { index: n, size: n })
+        );
+    }
+
+    #[test]
+    fn hiding_commitments() {
+        let mut rng = test_rng();
+        let degree = 15;
+        let n = 8;
+        let kzg_bn254 = KZG::<Bn254>::insecure_setup_for_testing(
+            G1::rand(&mut rng),
+            G2::rand(&mut rng),
+            degree,
+            &mut rng,
+        );
+        let (pk, vk) = kzg_bn254.trim(degree, 1).unwrap();
+        let P = DensePolynomial::<F>::rand(n - 1, &mut rng);
+        // the blinded polynomial has degree n + hiding_bound
+        assert_eq!(
+            pk.commit_hiding(&P, n, degree - n + 1, &mut rng),
+            Err(SolvencyError::DegreeTooLarge {
+                degree: degree + 1,
+                max_degree: degree
+            })
+        );
+
+        // committing twice to the same polynomial gives unrelated commitments
+        let (commitment, blinded) = kzg_bn254.commit_hiding(&P, n, 2, &mut rng).unwrap();
+        let (other_commitment, _) = pk.commit_hiding(&P, n, 2, &mut rng).unwrap();
+        assert_ne!(commitment, other_commitment);
+        assert_ne!(commitment, kzg_bn254.commit(&P).unwrap());
+
+        // the rows over the domain open to the values of P
+        let (_, domain_elements) = get_omega_domain::<Bn254>(n).unwrap();
+        for x in &domain_elements {
+            let y = P.evaluate(x);
+            let opening = pk.open(&blinded, *x, y).unwrap();
+            assert!(vk.verify(y, *x, commitment, opening));
+            assert!(!vk.verify(y, *x, other_commitment, opening));
+            assert!(!vk.verify(y + F::from(1), *x, commitment, opening));
+        }
+
+        // outside of the domain, it is the blinded evaluation that is opened
+        let z = F::rand(&mut rng);
+        assert_ne!(blinded.evaluate(&z), P.evaluate(&z));
+    }
+
+    #[test]
+    fn degree_bounds() {
+        let mut rng = test_rng();
+        let kzg_bn254 = KZG::<Bn254>::insecure_setup_for_testing(
+            G1::rand(&mut rng),
+            G2::rand(&mut rng),
+            31,
+            &mut rng,
+        );
+        let bound = 15;
+        let key = kzg_bn254.degree_bound_key(bound).unwrap();
+        assert_eq!(key.max_degree, 31);
+        assert!(kzg_bn254.degree_bound_key(32).is_err());
+
+        let P = DensePolynomial::<F>::rand(bound, &mut rng);
+        let I = DensePolynomial::<F>::rand(bound - 3, &mut rng);
+        let commitments = [kzg_bn254.commit(&P).unwrap(), kzg_bn254.commit(&I).unwrap()];
+        let proofs = [
+            kzg_bn254.prove_degree_bound(&P, bound).unwrap(),
+            kzg_bn254.prove_degree_bound(&I, bound).unwrap(),
+        ];
+        assert!(key.verify(commitments[0], proofs[0]));
+        assert!(key.verify_all(&commitments, &proofs));
+        assert!(!key.verify_all(&commitments, &proofs[..1]));
+        assert!(!key.verify(commitments[1], proofs[0]));
+
+        // a polynomial above the bound can not be shifted within the powers of tau, and its
+        // truncated shift does not verify
+        let Q = DensePolynomial::<F>::rand(bound + 1, &mut rng);
+        assert_eq!(
+            kzg_bn254.prove_degree_bound(&Q, bound),
+            Err(SolvencyError::DegreeTooLarge {
+                degree: bound + 1,
+                max_degree: bound
+            })
+        );
+        let truncated = kzg_bn254
+            .prove_degree_bound(
+                &DensePolynomial::from_coefficients_slice(&Q.coeffs[..bound + 1]),
+                bound,
+            )
+            .unwrap();
+        assert!(!key.verify(kzg_bn254.commit(&Q).unwrap(), truncated));
+
+        // a prover holding the full SRS can shift a polynomial of degree 26 by X^5 and would pass
+        // a check against the powers up to 20, but not the one against the published powers
+        let R = DensePolynomial::<F>::rand(26, &mut rng);
+        let mut shifted = vec![F::zero(); 5];
+        shifted.extend_from_slice(&R.coeffs);
+        let forged = kzg_bn254
+            .commit(&DensePolynomial::from_coefficients_vec(shifted))
+            .unwrap();
+        assert!(!key.verify(kzg_bn254.commit(&R).unwrap(), forged));
+
+        // an SRS truncated from a larger transcript can not prove degree bounds
+        let mut truncated_srs = kzg_bn254.clone();
+        truncated_srs.published_degree = 40;
+        assert!(matches!(
+            truncated_srs.prove_degree_bound(&P, bound),
+            Err(SolvencyError::InvalidSrs(_))
+        ));
+    }
+
+    // Protocol-agnostic checks, run against every backend of the trait
+    fn check_commitment_scheme<PCS: PolynomialCommitmentScheme>(
+        params: &PCS::Params,
+        degree: usize,
+        rng: &mut impl Rng,
+    ) {
+        let (pk, vk) = PCS::trim(params, degree, 2).unwrap();
+        let polynomials: Vec<DensePolynomial<PCS::Field>> = (0..2)
+            .map(|_| DensePolynomial::rand(degree, rng))
+            .collect();
+        let commitments: Vec<PCS::Commitment> = polynomials
+            .iter()
+            .map(|polynomial| PCS::commit(&pk, polynomial).unwrap())
+            .collect();
+
+        let z = PCS::Field::rand(rng);
+        let y = polynomials[0].evaluate(&z);
+        let proof = PCS::open(&pk, &polynomials[0], z).unwrap();
+        assert!(PCS::verify(&vk, &commitments[0], z, y, &proof));
+        assert!(!PCS::verify(&vk, &commitments[1], z, y, &proof));
+        assert!(!PCS::verify(&vk, &commitments[0], z, y + PCS::Field::from(1u64), &proof));
+
+        let point_sets: Vec<Vec<PCS::Field>> = vec![
+            (0..3).map(|_| PCS::Field::rand(rng)).collect(),
+            vec![PCS::Field::rand(rng)],
+        ];
+        let mut values: Vec<Vec<PCS::Field>> = polynomials
+            .iter()
+            .zip(&point_sets)
+            .map(|(polynomial, points)| points.iter().map(|x| polynomial.evaluate(x)).collect())
+            .collect();
+        let references: Vec<&DensePolynomial<PCS::Field>> = polynomials.iter().collect();
+        let mut transcript = Transcript::new(b"test");
+        let proof =
+            PCS::batch_open(&pk, &references, &commitments, &point_sets, &mut transcript).unwrap();
+        let check = |values: &[Vec<PCS::Field>]| {
+            let mut transcript = Transcript::new(b"test");
+            PCS::batch_verify(&vk, &commitments, &point_sets, values, &mut transcript, &proof)
+        };
+        assert!(check(&values));
+        values[0][1] += PCS::Field::from(1u64);
+        assert!(!check(&values));
+    }
+
+    #[test]
+    fn commitment_scheme_trait() {
+        let mut rng = test_rng();
+        let kzg_bn254 = KZG::<Bn254>::insecure_setup_for_testing(
+            G1::rand(&mut rng),
+            G2::rand(&mut rng),
+            15,
+            &mut rng,
+        );
+        check_commitment_scheme::<KZG<Bn254>>(&kzg_bn254, 15, &mut rng);
+        check_commitment_scheme::<IPA<Bn254>>(&IPA::setup(15), 15, &mut rng);
+
+        let kzg_bls12_381 = KZG::<Bls12_381>::insecure_setup_for_testing(
+            ark_bls12_381::G1Projective::rand(&mut rng),
+            ark_bls12_381::G2Projective::rand(&mut rng),
+            15,
+            &mut rng,
+        );
+        check_commitment_scheme::<KZG<Bls12_381>>(&kzg_bls12_381, 15, &mut rng);
+        check_commitment_scheme::<IPA<Bls12_381>>(&IPA::setup(15), 15, &mut rng);
+    }
+
+    #[test]
+    fn transparent_commitments() {
+        let mut rng = test_rng();
+        let users: Vec<User> = (0..4)
+            .map(|i| User {
+                username: i,
+                balance: rng.gen_range(0..1 << 14),
+                salt: rng.gen(),
+            })
+            .collect();
+        let total = total_balance(&users);
+        let (p_witness, _) =
+            generate_witness::<Bn254>(users.clone(), total, &WitnessLayout::default()).unwrap();
+        let P = lagrange_interpolate::<Bn254>(&p_witness).unwrap();
+        let (_, domain_elements) = get_omega_domain::<Bn254>(p_witness.len()).unwrap();
+
+        // the generators only depend on their index, so setups of different sizes agree
+        let ipa = IPA::<Bn254>::setup(P.degree());
+        assert_eq!(ipa, IPA::setup(P.degree()));
+        assert_eq!(ipa.generators[..16], IPA::<Bn254>::setup(15).generators[..]);
+        // a trimmed setup and a fresh one of the same size verify each other's proofs
+        let trimmed = IPA::<Bn254>::setup(31).trim(15).unwrap();
+        let fresh = IPA::<Bn254>::setup(15);
+        assert_eq!(trimmed, fresh);
+        let Q = DensePolynomial::<F>::rand(15, &mut rng);
+        let z = F::rand(&mut rng);
+        let q_commitment = fresh.commit(&Q).unwrap();
+        let proof = trimmed.open(&Q, z, Q.evaluate(&z)).unwrap();
+        assert!(fresh.verify(q_commitment, z, Q.evaluate(&z), &proof));
+        let proof = fresh.open(&Q, z, Q.evaluate(&z)).unwrap();
+        assert!(trimmed.verify(q_commitment, z, Q.evaluate(&z), &proof));
+        let commitment = ipa.commit(&P).unwrap();
+        assert_eq!(commitment, ipa.trim(P.degree()).unwrap().commit(&P).unwrap());
+
+        // balance row of the third user
+        let x = domain_elements[5];
+        let y = F::from(users[2].balance);
+        let proof = ipa.open(&P, x, y).unwrap();
+        assert_eq!(proof.l.len(), ipa.generators.len().trailing_zeros() as usize);
+        assert!(ipa.verify(commitment, x, y, &proof));
+        assert!(!ipa.verify(commitment, x, y + F::from(1), &proof));
+        assert!(!ipa.verify(commitment, domain_elements[3], y, &proof));
+        let mut bad = proof.clone();
+        bad.a += F::from(1);
+        assert!(!ipa.verify(commitment, x, y, &bad));
+        bad = proof.clone();
+        bad.l.pop();
+        assert!(!ipa.verify(commitment, x, y, &bad));
+        assert!(ipa.commit(&DensePolynomial::rand(ipa.generators.len(), &mut rng)).is_err());
+    }
+
+    // Witness of I(X) for users with the given balances, all of them with the same username
+    fn liabilities_witness(
+        balances: &[u64],
+        declared_total: u128,
+        layout: &WitnessLayout,
+    ) -> Vec<F> {
+        let users: Vec<User> = balances
+            .iter()
+            .map(|balance| User {
+                username: 1,
+                balance: *balance,
+                salt: 0,
+            })
+            .collect();
+        generate_witness::<Bn254>(users, declared_total, layout).unwrap().1
+    }
+
+    fn check_constraint_system<PCS: PolynomialCommitmentScheme<Field = F>>(params: &PCS::Params) {
+        // three users, so that the domain has padding rows
+        let i_witness = liabilities_witness(&[4, 9, 6], 19, &WitnessLayout::default());
+        let system = ConstraintSystem::new(WitnessLayout::default(), 3, 19).unwrap();
+        let (pk, vk) = PCS::trim(params, system.quotient_degree(), 1).unwrap();
+        let domain = GeneralEvaluationDomain::<F>::new(system.domain_size()).unwrap();
+        let I = DensePolynomial::from_coefficients_vec(domain.ifft(&i_witness));
+        let i_commitment = PCS::commit(&pk, &I).unwrap();
+
+        let mut transcript = Transcript::new(b"test");
+        let proof = system.prove::<PCS>(&pk, &i_witness, &mut transcript).unwrap();
+        let verify_with = |system: &ConstraintSystem<F>,
+                           i_commitment: &PCS::Commitment,
+                           proof: &ConstraintProof<PCS>| {
+            let mut transcript = Transcript::new(b"test");
+            system.verify::<PCS>(&vk, i_commitment, proof, &mut transcript)
+        };
+        let verify = |system: &ConstraintSystem<F>, proof: &ConstraintProof<PCS>| {
+            verify_with(system, &i_commitment, proof)
+        };
+        assert!(verify(&system, &proof));
+        let mut bytes = vec![];
+        proof.serialize_compressed(&mut bytes).unwrap();
+        assert_eq!(ConstraintProof::<PCS>::deserialize_compressed(&bytes[..]).unwrap(), proof);
+
+        // the declared total is a public input, and the evaluations are bound to the commitments
+        let other_total = ConstraintSystem::new(WitnessLayout::default(), 3, 18).unwrap();
+        assert!(!verify(&other_total, &proof));
+        let mut bad = proof.clone();
+        bad.evaluations[3] += F::from(1);
+        assert!(!verify(&system, &bad));
+        bad = proof.clone();
+        bad.evaluations.pop();
+        assert!(!verify(&system, &bad));
+        bad = proof.clone();
+        bad.quotient = i_commitment.clone();
+        assert!(!verify(&system, &bad));
+
+        // the proof is bound to the commitment to I(X), which the verifier absorbs itself
+        let other_witness = liabilities_witness(&[9, 4, 6], 19, &WitnessLayout::default());
+        let other_I = DensePolynomial::from_coefficients_vec(domain.ifft(&other_witness));
+        let other_commitment = PCS::commit(&pk, &other_I).unwrap();
+        assert!(!verify_with(&system, &other_commitment, &proof));
+    }
+
+    #[test]
+    fn constraint_system() {
+        let mut rng = test_rng();
+        let layout = WitnessLayout::default();
+        let system = ConstraintSystem::new(layout, 3, 19).unwrap();
+        assert_eq!(system.domain_size(), 64);
+        // 3 does not divide the total, and the first running total 4 - 19 / 3 is negative
+        let i_witness = liabilities_witness(&[4, 9, 6], 19, &layout);
+        assert_eq!(i_witness[15], -F::from(7) / F::from(3));
+        assert_eq!(i_witness[47], F::zero());
+        assert_eq!(system.check_witness(&i_witness), Ok(()));
+
+        // a cell that is not twice the previous one plus a bit, a wrong running total, and
+        // balances that do not sum up to the declared total
+        let mut bad = i_witness.clone();
+        bad[3] = F::from(5);
+        let mut transcript = Transcript::new(b"test");
+        let kzg_bn254 = KZG::<Bn254>::insecure_setup_for_testing(
+            G1::rand(&mut rng),
+            G2::rand(&mut rng),
+            system.quotient_degree(),
+            &mut rng,
+        );
+        let (pk, _) = kzg_bn254.trim(system.quotient_degree(), 1).unwrap();
+        assert_eq!(
+            system.prove::<KZG<Bn254>>(&pk, &bad, &mut transcript),
+            Err(SolvencyError::UnsatisfiedConstraint {
+                gate: "bits",
+                row: 2
+            })
+        );
+        let mut bad = i_witness.clone();
+        bad[16 + 15] += F::from(1);
+        assert_eq!(
+            system.check_witness(&bad),
+            Err(SolvencyError::UnsatisfiedConstraint {
+                gate: "running total",
+                row: 31
+            })
+        );
+        assert_eq!(
+            system.check_witness(&liabilities_witness(&[4, 9, 7], 19, &layout)),
+            Err(SolvencyError::UnsatisfiedConstraint {
+                gate: "final total",
+                row: 47
+            })
+        );
+        assert_eq!(
+            system.check_witness(&vec![F::zero(); 65]),
+            Err(SolvencyError::LengthMismatch {
+                expected: 64,
+                found: 65
+            })
+        );
+
+        check_constraint_system::<KZG<Bn254>>(&kzg_bn254);
+        check_constraint_system::<IPA<Bn254>>(&IPA::setup(system.quotient_degree()));
+    }
+
     #[test]
     fn compute_Q() {
         let mut rng = test_rng();
@@ -115,12 +1114,12 @@ mod tests {
         // Build polynomial L(X), that consists into the "opening" of (username, balance)
         // we want the L(X) interpolated polynomial to be defined over the same domain of P
         // When evaluated at omega^2 and omega^3, it needs to be equal to P(omega^2) and P(omega^3)
-        let l_omegas = p_omegas.clone();
+        let l_omegas = p_omegas;
         let mut l_evaluations = vec![];
         for (i, element) in domain_elements.iter().enumerate() {
             if i == 2 || i == 3 {
                 // at omega^2 and omega^3, we want to have P(omega^2) and P(omega^3)
-                let eval = P.evaluate(&element);
+                let eval = P.evaluate(element);
                 l_evaluations.push(eval);
             } else {
                 l_evaluations.push(F::zero());
@@ -142,7 +1141,7 @@ mod tests {
         let Z = &l_root_username * &l_root_balance;
 
         // Build final polynomial Q(X)
-        let Q = &(&P - &L) / (&Z);
+        let _Q = &(&P - &L) / (&Z);
 
         // L(X) and Z(X) do not have the same coeffs
         assert_ne!(L.coeffs(), Z.coeffs());
@@ -162,32 +1161,125 @@ mod tests {
         assert_eq!(Z.evaluate(&domain_elements[3]), F::zero());
     }
 
-    fn test_lagrange() {
-        let balances = vec![
-            F::from(20),
-            F::from(50),
-            F::from(10),
-            F::from(164),
-            F::from(870),
-            F::from(6),
-            F::from(270),
-            F::from(90),
-        ];
+    fn check_lagrange<E: Pairing>() {
+        let balances: Vec<E::ScalarField> = [20u64, 50, 10, 164, 870, 6, 270, 90]
+            .iter()
+            .map(|balance| E::ScalarField::from(*balance))
+            .collect();
 
-        let poly = lagrange_interpolate(&balances);
+        let poly = lagrange_interpolate::<E>(&balances).unwrap();
 
-        let omegas = GeneralEvaluationDomain::<F>::new(1 << 3).unwrap();
+        let omegas = GeneralEvaluationDomain::<E::ScalarField>::new(1 << 3).unwrap();
 
         for (i, omega) in omegas.elements().enumerate() {
             assert_eq!(poly.evaluate(&omega), balances[i]);
         }
     }
 
+    #[test]
+    fn test_lagrange() {
+        check_lagrange::<Bn254>();
+        check_lagrange::<Bls12_381>();
+    }
+
+    // The whole flow of the example: witness, commitments in both forms, user inclusion proofs,
+    // the multi-point opening of constraint 3 and the serialization of what is published
+    fn check_solvency_pipeline<E: CurveId>(rng: &mut impl Rng) {
+        let n_users = 4;
+        let users: Vec<User> = (0..n_users)
+            .map(|i| User {
+                username: i,
+                balance: rng.gen_range(0..1 << 14),
+                salt: rng.gen(),
+            })
+            .collect();
+        let layout = WitnessLayout::default();
+        let total = total_balance(&users);
+        let (p_witness, i_witness) = generate_witness::<E>(users.clone(), total, &layout).unwrap();
+        let P = lagrange_interpolate::<E>(&p_witness).unwrap();
+        let I = lagrange_interpolate::<E>(&i_witness).unwrap();
+        let (_, domain_elements) = get_omega_domain::<E>(i_witness.len()).unwrap();
+        for (x, y) in domain_elements.iter().zip(&i_witness) {
+            assert_eq!(I.evaluate(x), *y);
+        }
+
+        let degree = domain_elements.len() - 1;
+        let kzg =
+            KZG::<E>::insecure_setup_for_testing(E::G1::rand(rng), E::G2::rand(rng), degree, rng);
+        // enough points to open the balance cells of a user's row
+        let (_, vk) = kzg.trim(degree, layout.bits + 1).unwrap();
+        let lagrange_key = kzg.lagrange_key(domain_elements.len()).unwrap();
+        let p_commitment = kzg.commit(&P).unwrap();
+        let i_commitment = lagrange_key.commit_evaluations(&i_witness).unwrap();
+        assert_eq!(p_commitment, lagrange_key.commit_evaluations(&p_witness).unwrap());
+        assert_eq!(i_commitment, kzg.commit(&I).unwrap());
+
+        // inclusion of (username, balance) of every user
+        let user_proofs = kzg.open_all_users(&P, domain_elements.len(), n_users as usize).unwrap();
+        for (i, pi) in user_proofs.iter().enumerate() {
+            let points = vec![domain_elements[2 * i], domain_elements[2 * i + 1]];
+            let L = interpolate_points::<E>(&points, &[p_witness[2 * i], p_witness[2 * i + 1]])
+                .unwrap();
+            let Z = build_zero_polynomial::<E>(&points).unwrap();
+            assert!(vk.verify_multi_open(p_commitment, *pi, &Z, &L));
+        }
+
+        // the cells of the second user's row, opened from I(X), decompose their balance
+        let start = layout.row_width + layout.decomposition_offset();
+        let rows = start..layout.balance_row(1) + 1;
+        let points = domain_elements[rows.clone()].to_vec();
+        let cells = &i_witness[rows];
+        let L = interpolate_points::<E>(&points, cells).unwrap();
+        let Z = build_zero_polynomial::<E>(&points).unwrap();
+        let pi = kzg.multi_open(&I, &L, points).unwrap();
+        assert!(vk.verify_multi_open(i_commitment, pi, &Z, &L));
+        assert_eq!(verify_balance_cells(cells, users[1].balance, &layout), Ok(()));
+
+        // the balances of I(X) and P(X) match
+        let point_sets = vec![
+            (0..n_users as usize).map(|i| domain_elements[layout.balance_row(i)]).collect(),
+            (0..n_users as usize).map(|i| domain_elements[2 * i + 1]).collect(),
+        ];
+        let balances: Vec<Evaluation<E>> = users
+            .iter()
+            .map(|user| Evaluation::Scalar(E::ScalarField::from(user.balance)))
+            .collect();
+        let commitments = [i_commitment, p_commitment];
+        let mut transcript = Transcript::new(b"test");
+        let proof = kzg
+            .multi_point_open(&[&I, &P], &commitments, &point_sets, &mut transcript)
+            .unwrap();
+        assert!(vk.verify_multi_point_open(
+            &commitments,
+            &point_sets,
+            &[balances.clone(), balances],
+            &mut Transcript::new(b"test"),
+            &proof,
+        ));
+
+        let bytes = to_bytes::<E, _>(ObjectKind::VerifierKey, &vk, Compress::Yes).unwrap();
+        assert_eq!(from_bytes::<E, VerifierKey<E>>(ObjectKind::VerifierKey, &bytes).unwrap(), vk);
+    }
+
+    #[test]
+    fn solvency_pipeline() {
+        let mut rng = test_rng();
+        check_solvency_pipeline::<Bn254>(&mut rng);
+        check_solvency_pipeline::<Bls12_381>(&mut rng);
+
+        // objects of one curve can not be read over the other
+        let commitment = G1::rand(&mut rng);
+        let bytes =
+            to_bytes::<Bn254, _>(ObjectKind::Commitment, &commitment, Compress::Yes).unwrap();
+        let read = from_bytes::<Bls12_381, ark_bls12_381::G1Projective>(ObjectKind::Commitment, &bytes);
+        assert!(read.is_err());
+    }
+
     #[test]
     fn test_witness_gen() {
         let mut rng = test_rng();
 
-        let balances = vec![20, 50, 10, 164, 870, 6, 270, 90];
+        let balances = [20, 50, 10, 164, 870, 6, 270, 90];
 
         let users = balances
             .iter()
@@ -199,7 +1291,10 @@ mod tests {
             })
             .collect::<Vec<User>>();
 
-        let (p_witness, i_witness) = generate_witness::<Bn254>(users.clone()).unwrap();
+        let layout = WitnessLayout::default();
+        let total = total_balance(&users);
+        let (p_witness, i_witness) =
+            generate_witness::<Bn254>(users.clone(), total, &layout).unwrap();
 
         // check that p witnesss and i_witness are built correctly
         for (i, user) in users.iter().enumerate() {
@@ -208,10 +1303,498 @@ mod tests {
             assert_eq!(i_witness[14 + 16 * i], F::from(user.balance));
             assert_eq!(i_witness[16 * i], F::zero());
 
-            // The last running total should be equal to 0
+            // the cells are the prefixes of the binary decomposition of the balance
+            for j in 0..15 {
+                assert_eq!(i_witness[16 * i + j], F::from(user.balance >> (14 - j)));
+            }
+            let cells = &i_witness[16 * i..16 * i + 15];
+            assert_eq!(verify_balance_cells(cells, user.balance, &layout), Ok(()));
+
+            // The running totals are offset by the average, and the last one is 0
+            let prefix = total_balance(&users[..=i]);
+            let average = F::from(total) / F::from(users.len() as u64);
+            assert_eq!(i_witness[15 + 16 * i], F::from(prefix) - F::from(i as u64 + 1) * average);
             if i == users.len() - 1 {
                 assert_eq!(i_witness[15 + 16 * i], F::zero());
             }
         }
     }
+
+    #[test]
+    fn balance_cells() {
+        // the largest balance sets all the bits
+        let layout = WitnessLayout::default();
+        let largest = (1 << 14) - 1;
+        let i_witness = liabilities_witness(&[largest], largest as u128, &layout);
+        let cells: Vec<F> = (0..15).map(|j| F::from((1u32 << j) - 1)).collect();
+        assert_eq!(i_witness[..15], cells[..]);
+        assert_eq!(verify_balance_cells(&cells, largest, &layout), Ok(()));
+
+        let mut bad = cells.clone();
+        bad[0] = F::from(1);
+        assert_eq!(
+            verify_balance_cells(&bad, largest, &layout),
+            Err(SolvencyError::UnsatisfiedConstraint {
+                gate: "first cell",
+                row: 0
+            })
+        );
+        bad = cells.clone();
+        bad[5] = F::from(100);
+        assert_eq!(
+            verify_balance_cells(&bad, largest, &layout),
+            Err(SolvencyError::UnsatisfiedConstraint {
+                gate: "bits",
+                row: 4
+            })
+        );
+        assert_eq!(
+            verify_balance_cells(&cells, largest - 1, &layout),
+            Err(SolvencyError::UnsatisfiedConstraint {
+                gate: "balance",
+                row: 14
+            })
+        );
+        assert_eq!(
+            verify_balance_cells(&i_witness, largest, &layout),
+            Err(SolvencyError::LengthMismatch {
+                expected: 15,
+                found: 16
+            })
+        );
+    }
+
+    #[test]
+    fn witness_layout() {
+        let mut rng = test_rng();
+        // 64-bit balances, with the running total before the balance cells and padding rows at
+        // the start and the end of each block
+        let layout = WitnessLayout {
+            bits: 64,
+            row_width: 72,
+            balance_offset: 66,
+            total_offset: 1,
+        };
+        assert_eq!(layout.validate(), Ok(()));
+        let balances = [u64::MAX, 1 << 63, 5];
+        let total = balances.iter().map(|balance| *balance as u128).sum::<u128>();
+        let i_witness = liabilities_witness(&balances, total, &layout);
+        assert_eq!(i_witness.len(), 3 * 72);
+        for (i, balance) in balances.iter().enumerate() {
+            let cells = &i_witness[72 * i + 2..=layout.balance_row(i)];
+            assert_eq!(cells[0], F::zero());
+            assert_eq!(cells[64], F::from(*balance));
+            assert_eq!(verify_balance_cells(cells, *balance, &layout), Ok(()));
+        }
+        assert_eq!(i_witness[layout.total_row(2)], F::zero());
+
+        let system = ConstraintSystem::new(layout, 3, total).unwrap();
+        assert_eq!(system.domain_size(), 256);
+        assert_eq!(system.check_witness(&i_witness), Ok(()));
+        let wrong_total = liabilities_witness(&balances, total - 1, &layout);
+        assert_eq!(
+            system.check_witness(&wrong_total),
+            Err(SolvencyError::UnsatisfiedConstraint {
+                gate: "running total",
+                row: layout.total_row(0)
+            })
+        );
+        let kzg_bn254 = KZG::<Bn254>::insecure_setup_for_testing(
+            G1::rand(&mut rng),
+            G2::rand(&mut rng),
+            system.quotient_degree(),
+            &mut rng,
+        );
+        let (pk, vk) = kzg_bn254.trim(system.quotient_degree(), 1).unwrap();
+        let domain = GeneralEvaluationDomain::<F>::new(system.domain_size()).unwrap();
+        let I = DensePolynomial::from_coefficients_vec(domain.ifft(&i_witness));
+        let i_commitment = kzg_bn254.commit(&I).unwrap();
+        let mut transcript = Transcript::new(b"test");
+        let proof = system.prove::<KZG<Bn254>>(&pk, &i_witness, &mut transcript).unwrap();
+        let mut transcript = Transcript::new(b"test");
+        assert!(system.verify::<KZG<Bn254>>(&vk, &i_commitment, &proof, &mut transcript));
+        // the layout is a public input
+        let default = ConstraintSystem::new(WitnessLayout::default(), 3, total).unwrap();
+        let mut transcript = Transcript::new(b"test");
+        assert!(!default.verify::<KZG<Bn254>>(&vk, &i_commitment, &proof, &mut transcript));
+
+        // balances that do not fit the bits, and layouts whose cells do not fit their rows
+        let users = vec![User {
+            username: 1,
+            balance: 1 << 20,
+            salt: 0,
+        }];
+        let narrow = WitnessLayout {
+            bits: 20,
+            ..WitnessLayout::default()
+        };
+        assert!(matches!(
+            generate_witness::<Bn254>(users.clone(), 1 << 20, &narrow),
+            Err(SolvencyError::InvalidLayout(_))
+        ));
+        for bad in [
+            WitnessLayout {
+                bits: 65,
+                row_width: 80,
+                balance_offset: 70,
+                total_offset: 0,
+            },
+            WitnessLayout {
+                row_width: 15,
+                ..WitnessLayout::default()
+            },
+            WitnessLayout {
+                total_offset: 3,
+                ..WitnessLayout::default()
+            },
+            WitnessLayout {
+                bits: 0,
+                ..WitnessLayout::default()
+            },
+        ] {
+            assert!(matches!(bad.validate(), Err(SolvencyError::InvalidLayout(_))));
+            assert!(ConstraintSystem::<F>::new(bad, 1, 0).is_err());
+        }
+        let wide = WitnessLayout {
+            bits: 20,
+            row_width: 24,
+            balance_offset: 20,
+            total_offset: 21,
+        };
+        assert_eq!(
+            generate_witness::<Bn254>(users, 1 << 20, &wide),
+            Err(SolvencyError::InvalidBalance {
+                user: 0,
+                balance: 1 << 20
+            })
+        );
+    }
+
+    #[test]
+    fn test_witness_gen_errors() {
+        assert_eq!(
+            generate_witness::<Bn254>(vec![], 0, &WitnessLayout::default()),
+            Err(SolvencyError::EmptyUsers)
+        );
+        assert_eq!(average_balance::<F>(19, 0), Err(SolvencyError::EmptyUsers));
+        assert_eq!(average_balance::<F>(19, 3), Ok(F::from(19) / F::from(3)));
+
+        let users = vec![
+            User {
+                username: 1,
+                balance: 20,
+                salt: 2,
+            },
+            User {
+                username: 3,
+                balance: 1 << 14,
+                salt: 4,
+            },
+        ];
+        assert_eq!(
+            generate_witness::<Bn254>(users, 20 + (1 << 14), &WitnessLayout::default()),
+            Err(SolvencyError::InvalidBalance {
+                user: 1,
+                balance: 1 << 14
+            })
+        );
+
+        assert_eq!(
+            build_zero_polynomial::<Bn254>(&[]),
+            Err(SolvencyError::EmptyRoots)
+        );
+    }
+
+    // Writes a snarkjs style ptau file of the given power, cut down from a ceremony of
+    // `ceremony_power`, returns it with the SRS it holds
+    fn write_test_ptau(
+        power: u32,
+        ceremony_power: u32,
+        rng: &mut impl Rng,
+    ) -> (Vec<u8>, KZG<Bn254>) {
+        let n_g1 = (1 << (power + 1)) - 1;
+        let n_g2 = 1 << power;
+        let kzg_bn254 =
+            KZG::<Bn254>::insecure_setup_for_testing(G1::generator(), G2::generator(), n_g1 - 1, rng);
+
+        let write_fq = |bytes: &mut Vec<u8>, x: &Fq| {
+            // the inner representation of arkworks field elements is already in Montgomery form
+            for limb in x.0 .0 {
+                bytes.extend_from_slice(&limb.to_le_bytes());
+            }
+        };
+        let mut header = vec![];
+        header.extend_from_slice(&32u32.to_le_bytes());
+        for limb in Fq::MODULUS.0 {
+            header.extend_from_slice(&limb.to_le_bytes());
+        }
+        header.extend_from_slice(&power.to_le_bytes());
+        header.extend_from_slice(&ceremony_power.to_le_bytes());
+        let mut tau_g1 = vec![];
+        for point in &kzg_bn254.crs {
+            write_fq(&mut tau_g1, &point.x);
+            write_fq(&mut tau_g1, &point.y);
+        }
+        let mut tau_g2 = vec![];
+        for point in &kzg_bn254.crs_2[..n_g2] {
+            write_fq(&mut tau_g2, &point.x.c0);
+            write_fq(&mut tau_g2, &point.x.c1);
+            write_fq(&mut tau_g2, &point.y.c0);
+            write_fq(&mut tau_g2, &point.y.c1);
+        }
+
+        let mut bytes = b"ptau".to_vec();
+        bytes.extend_from_slice(&1u32.to_le_bytes());
+        bytes.extend_from_slice(&4u32.to_le_bytes());
+        // sections are not necessarily ordered, and some of them are not used by the loader
+        for (section_type, content) in [(7u32, vec![1u8; 10]), (1, header), (3, tau_g2), (2, tau_g1)] {
+            bytes.extend_from_slice(&section_type.to_le_bytes());
+            bytes.extend_from_slice(&(content.len() as u64).to_le_bytes());
+            bytes.extend_from_slice(&content);
+        }
+        (bytes, kzg_bn254)
+    }
+
+    #[test]
+    fn load_srs_from_ptau() {
+        let mut rng = test_rng();
+        let (bytes, expected) = write_test_ptau(2, 2, &mut rng);
+
+        let loaded = read_ptau(Cursor::new(&bytes), 5).unwrap();
+        assert_eq!(loaded.degree, 5);
+        // degree bounds are checked against the largest power of the ceremony, not the last one
+        // read
+        assert_eq!(loaded.published_degree, 6);
+        assert_eq!(loaded.crs, expected.crs[..6].to_vec());
+        // a power 2 transcript only has 4 powers in G2
+        assert_eq!(loaded.crs_2, expected.crs_2[..4].to_vec());
+        assert_eq!(loaded.vk, expected.vk);
+        // which limits the multi-openings, not the degree
+        assert!(loaded.trim(5, 3).is_ok());
+        assert!(matches!(loaded.trim(5, 4), Err(SolvencyError::InvalidSrs(_))));
+        assert!(matches!(
+            loaded.trim(6, 3),
+            Err(SolvencyError::DegreeTooLarge { .. })
+        ));
+
+        let path = std::env::temp_dir().join("kzg_solvency_test.ptau");
+        std::fs::write(&path, &bytes).unwrap();
+        let from_file = load_ptau(&path, 3).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let polynomial: DensePolynomial<F> = DenseUVPolynomial::rand(3, &mut rng);
+        let z = F::rand(&mut rng);
+        let y = polynomial.evaluate(&z);
+        let commitment = from_file.commit(&polynomial).unwrap();
+        let pi = from_file.open(&polynomial, z, y).unwrap();
+        assert!(from_file.verify(y, z, commitment, pi));
+
+        // the transcript holds 7 powers in G1
+        assert!(matches!(
+            read_ptau(Cursor::new(&bytes), 7),
+            Err(SolvencyError::DegreeTooLarge { .. })
+        ));
+
+        // flip a byte of the last G1 point read
+        let mut corrupted = bytes.clone();
+        let len = corrupted.len();
+        corrupted[len - 7 * 64 + 3 * 64 + 5] ^= 1;
+        assert!(matches!(
+            read_ptau(Cursor::new(&corrupted), 3),
+            Err(SolvencyError::InvalidSrs(_))
+        ));
+        assert!(read_ptau(Cursor::new(&corrupted), 2).is_ok());
+    }
+
+    #[test]
+    fn ptau_cut_down_from_a_larger_ceremony() {
+        let mut rng = test_rng();
+        // a power 2 file out of a power 4 ceremony, whose transcript holds powers up to tau^30
+        let (bytes, expected) = write_test_ptau(2, 4, &mut rng);
+        let loaded = read_ptau(Cursor::new(&bytes), 6).unwrap();
+        assert_eq!(loaded.crs, expected.crs);
+        assert_eq!(loaded.published_degree, 30);
+
+        // the file holds too few powers to prove degree bounds against the ceremony
+        let P = DensePolynomial::<F>::rand(3, &mut rng);
+        assert!(matches!(
+            loaded.prove_degree_bound(&P, 3),
+            Err(SolvencyError::InvalidSrs(_))
+        ));
+        assert!(matches!(
+            loaded.degree_bound_key(3),
+            Err(SolvencyError::InvalidSrs(_))
+        ));
+
+        let (bytes, _) = write_test_ptau(2, 1, &mut rng);
+        assert!(matches!(
+            read_ptau(Cursor::new(&bytes), 3),
+            Err(SolvencyError::InvalidSrs(_))
+        ));
+    }
+
+    #[test]
+    fn srs_verification() {
+        let mut rng = test_rng();
+        let kzg_bn254 = KZG::<Bn254>::insecure_setup_for_testing(
+            G1::rand(&mut rng),
+            G2::rand(&mut rng),
+            16,
+            &mut rng,
+        );
+        assert_eq!(kzg_bn254.verify_srs(&mut rng), Ok(()));
+
+        // an SRS read from a ptau file verifies as well
+        let (bytes, _) = write_test_ptau(3, 3, &mut rng);
+        let loaded = read_ptau(Cursor::new(&bytes), 10).unwrap();
+        assert_eq!(loaded.verify_srs(&mut rng), Ok(()));
+
+        // a power of tau in G1 which is off
+        let mut bad = KZG::<Bn254>::from_powers(kzg_bn254.crs.clone(), kzg_bn254.crs_2.clone()).unwrap();
+        bad.crs[7] = (bad.crs[7] * F::from(2)).into();
+        assert_eq!(
+            bad.verify_srs(&mut rng),
+            Err(SolvencyError::SrsMismatch {
+                group: "g1",
+                index: 7
+            })
+        );
+
+        // a power of tau in G2 which does not match the G1 one
+        let mut bad = KZG::<Bn254>::from_powers(kzg_bn254.crs.clone(), kzg_bn254.crs_2.clone()).unwrap();
+        bad.crs_2[5] = bad.crs_2[6];
+        assert_eq!(
+            bad.verify_srs(&mut rng),
+            Err(SolvencyError::SrsMismatch {
+                group: "g2",
+                index: 5
+            })
+        );
+
+        // vk has to be [tau]_2
+        let mut bad = KZG::<Bn254>::from_powers(kzg_bn254.crs.clone(), kzg_bn254.crs_2.clone()).unwrap();
+        bad.vk *= F::from(3);
+        assert_eq!(
+            bad.verify_srs(&mut rng),
+            Err(SolvencyError::SrsMismatch {
+                group: "vk",
+                index: 1
+            })
+        );
+    }
+
+    #[test]
+    fn setup_ceremony() {
+        let mut rng = test_rng();
+        let dir = std::env::temp_dir().join("kzg_solvency_ceremony");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = |name: &str| dir.join(name);
+
+        let initial = Powers::<Bn254>::new(G1::generator(), G2::generator(), 8);
+        initial.write_to_file(path("powers_0")).unwrap();
+
+        // three participants contribute one after the other, each one only reads the previous file
+        let mut contribution_files = vec![];
+        for i in 0..3 {
+            let before = path(&format!("powers_{}", i));
+            let after = path(&format!("powers_{}", i + 1));
+            let contribution = path(&format!("contribution_{}", i + 1));
+            contribute_file::<Bn254, _, _>(&before, &after, &contribution, &mut rng).unwrap();
+            verify_contribution_file::<Bn254, _, _>(&before, &after, &contribution, &mut rng)
+                .unwrap();
+            contribution_files.push(contribution);
+        }
+        verify_transcript_files::<Bn254, _, _>(
+            path("powers_0"),
+            &contribution_files,
+            path("powers_3"),
+            &mut rng,
+        )
+        .unwrap();
+
+        // contributions have to come in order
+        let contributions: Vec<Contribution<Bn254>> = contribution_files
+            .iter()
+            .map(|file| Contribution::read_from_file(file).unwrap())
+            .collect();
+        let last = Powers::<Bn254>::read_from_file(path("powers_3")).unwrap();
+        let swapped = vec![
+            contributions[1].clone(),
+            contributions[0].clone(),
+            contributions[2].clone(),
+        ];
+        assert!(verify_transcript(&initial, &swapped, &last, &mut rng).is_err());
+
+        // a contribution whose proof of knowledge was forged is rejected
+        let before = Powers::<Bn254>::read_from_file(path("powers_1")).unwrap();
+        let after = Powers::<Bn254>::read_from_file(path("powers_2")).unwrap();
+        let mut forged = contributions[1].clone();
+        forged.pok.response += F::from(1);
+        assert!(verify_contribution(&before, &after, &forged, &mut rng).is_err());
+        // the new powers have to be the ones the contribution proves
+        assert!(verify_contribution(&before, &last, &contributions[1], &mut rng).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // the ceremony output is a working SRS
+        let kzg_bn254 = last.into_kzg().unwrap();
+        let polynomial: DensePolynomial<F> = DenseUVPolynomial::rand(8, &mut rng);
+        let z = F::rand(&mut rng);
+        let y = polynomial.evaluate(&z);
+        let commitment = kzg_bn254.commit(&polynomial).unwrap();
+        let pi = kzg_bn254.open(&polynomial, z, y).unwrap();
+        assert!(kzg_bn254.verify(y, z, commitment, pi));
+    }
+
+    #[test]
+    fn serialization_round_trips() {
+        let mut rng = test_rng();
+        let kzg_bn254 = KZG::<Bn254>::insecure_setup_for_testing(
+            G1::rand(&mut rng),
+            G2::rand(&mut rng),
+            8,
+            &mut rng,
+        );
+        let (_, vk) = kzg_bn254.trim(8, 2).unwrap();
+        let polynomial: DensePolynomial<F> = DenseUVPolynomial::rand(8, &mut rng);
+        let z = F::rand(&mut rng);
+        let y = polynomial.evaluate(&z);
+        let commitment = kzg_bn254.commit(&polynomial).unwrap();
+        let pi = kzg_bn254.open(&polynomial, z, y).unwrap();
+
+        for compress in [Compress::Yes, Compress::No] {
+            let bytes = to_bytes::<Bn254, _>(ObjectKind::Srs, &kzg_bn254, compress).unwrap();
+            let srs: KZG<Bn254> = from_bytes::<Bn254, _>(ObjectKind::Srs, &bytes).unwrap();
+            assert_eq!(srs, kzg_bn254);
+
+            let json = to_json::<Bn254, _>(ObjectKind::VerifierKey, &vk, compress).unwrap();
+            let read_vk: VerifierKey<Bn254> =
+                from_json::<Bn254, _>(ObjectKind::VerifierKey, &json).unwrap();
+            assert_eq!(read_vk, vk);
+
+            let bytes = to_bytes::<Bn254, _>(ObjectKind::Commitment, &commitment, compress).unwrap();
+            let read_commitment: G1 = from_bytes::<Bn254, _>(ObjectKind::Commitment, &bytes).unwrap();
+            let json = to_json::<Bn254, _>(ObjectKind::OpeningProof, &pi, compress).unwrap();
+            let read_pi: G1 = from_json::<Bn254, _>(ObjectKind::OpeningProof, &json).unwrap();
+            assert!(read_vk.verify(y, z, read_commitment, read_pi));
+        }
+
+        // compressed points take half the space
+        let compressed = to_bytes::<Bn254, _>(ObjectKind::OpeningProof, &pi, Compress::Yes).unwrap();
+        let uncompressed = to_bytes::<Bn254, _>(ObjectKind::OpeningProof, &pi, Compress::No).unwrap();
+        assert_eq!(uncompressed.len() - compressed.len(), 32);
+
+        // a proof is not a commitment
+        assert!(from_bytes::<Bn254, G1>(ObjectKind::Commitment, &compressed).is_err());
+        // unknown versions are rejected
+        let mut future = compressed.clone();
+        future[4] += 1;
+        assert!(from_bytes::<Bn254, G1>(ObjectKind::OpeningProof, &future).is_err());
+        // so are points which are not on the curve, here (x, y + 1) or (x, y - 1)
+        let mut corrupted = uncompressed.clone();
+        let len = corrupted.len();
+        corrupted[len - 32] ^= 1;
+        assert!(from_bytes::<Bn254, G1>(ObjectKind::OpeningProof, &corrupted).is_err());
+        let json = to_json::<Bn254, _>(ObjectKind::OpeningProof, &pi, Compress::Yes).unwrap();
+        let tampered = json.replace("\"bn254\"", "\"bls12_381\"");
+        assert!(from_json::<Bn254, G1>(ObjectKind::OpeningProof, &tampered).is_err());
+    }
 }
@@ -0,0 +1,142 @@
+use std::fmt::Debug;
+
+use ark_ec::pairing::Pairing;
+use ark_ff::PrimeField;
+use ark_poly::univariate::DensePolynomial;
+use ark_poly::Polynomial;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+
+use crate::error::SolvencyError;
+use crate::kzg::{
+    Evaluation, KzgProver, KzgVerifier, MultiPointProof, ProverKey, VerifierKey, KZG,
+};
+use crate::transcript::Transcript;
+
+/// What the solvency protocol needs from a polynomial commitment scheme, so that the prover and
+/// verifier are written once for all the backends
+pub trait PolynomialCommitmentScheme {
+    type Field: PrimeField;
+    /// Public parameters out of the setup, trimmed into the prover and verifier keys
+    type Params;
+    type ProverKey;
+    type VerifierKey;
+    type Commitment: Clone + Debug + PartialEq + CanonicalSerialize + CanonicalDeserialize;
+    type Proof: Clone + Debug + PartialEq + CanonicalSerialize + CanonicalDeserialize;
+    type BatchProof: Clone + Debug + PartialEq + CanonicalSerialize + CanonicalDeserialize;
+
+    /// Keys to commit to polynomials of degree up to `max_degree` and to batch open them over
+    /// up to `max_points` points
+    fn trim(
+        params: &Self::Params,
+        max_degree: usize,
+        max_points: usize,
+    ) -> Result<(Self::ProverKey, Self::VerifierKey), SolvencyError>;
+
+    fn commit(
+        prover_key: &Self::ProverKey,
+        polynomial: &DensePolynomial<Self::Field>,
+    ) -> Result<Self::Commitment, SolvencyError>;
+
+    /// Proof that `polynomial` evaluates to `polynomial(z)` at `z`
+    fn open(
+        prover_key: &Self::ProverKey,
+        polynomial: &DensePolynomial<Self::Field>,
+        z: Self::Field,
+    ) -> Result<Self::Proof, SolvencyError>;
+
+    fn verify(
+        verifier_key: &Self::VerifierKey,
+        commitment: &Self::Commitment,
+        z: Self::Field,
+        y: Self::Field,
+        proof: &Self::Proof,
+    ) -> bool;
+
+    /// Proof that each `polynomials[i]` evaluates to its values over `point_sets[i]`. The
+    /// challenges are squeezed from the `transcript` once the `commitments` to the polynomials,
+    /// the points and the values are appended to it.
+    fn batch_open(
+        prover_key: &Self::ProverKey,
+        polynomials: &[&DensePolynomial<Self::Field>],
+        commitments: &[Self::Commitment],
+        point_sets: &[Vec<Self::Field>],
+        transcript: &mut Transcript,
+    ) -> Result<Self::BatchProof, SolvencyError>;
+
+    fn batch_verify(
+        verifier_key: &Self::VerifierKey,
+        commitments: &[Self::Commitment],
+        point_sets: &[Vec<Self::Field>],
+        values: &[Vec<Self::Field>],
+        transcript: &mut Transcript,
+        proof: &Self::BatchProof,
+    ) -> bool;
+}
+
+impl<E: Pairing> PolynomialCommitmentScheme for KZG<E> {
+    type Field = E::ScalarField;
+    type Params = KZG<E>;
+    type ProverKey = ProverKey<E>;
+    type VerifierKey = VerifierKey<E>;
+    type Commitment = E::G1;
+    type Proof = E::G1;
+    type BatchProof = MultiPointProof<E>;
+
+    fn trim(
+        params: &KZG<E>,
+        max_degree: usize,
+        max_points: usize,
+    ) -> Result<(ProverKey<E>, VerifierKey<E>), SolvencyError> {
+        params.trim(max_degree, max_points)
+    }
+
+    fn commit(
+        prover_key: &ProverKey<E>,
+        polynomial: &DensePolynomial<E::ScalarField>,
+    ) -> Result<E::G1, SolvencyError> {
+        prover_key.commit(polynomial)
+    }
+
+    fn open(
+        prover_key: &ProverKey<E>,
+        polynomial: &DensePolynomial<E::ScalarField>,
+        z: E::ScalarField,
+    ) -> Result<E::G1, SolvencyError> {
+        prover_key.open(polynomial, z, polynomial.evaluate(&z))
+    }
+
+    fn verify(
+        verifier_key: &VerifierKey<E>,
+        commitment: &E::G1,
+        z: E::ScalarField,
+        y: E::ScalarField,
+        proof: &E::G1,
+    ) -> bool {
+        verifier_key.verify(y, z, *commitment, *proof)
+    }
+
+    fn batch_open(
+        prover_key: &ProverKey<E>,
+        polynomials: &[&DensePolynomial<E::ScalarField>],
+        commitments: &[E::G1],
+        point_sets: &[Vec<E::ScalarField>],
+        transcript: &mut Transcript,
+    ) -> Result<MultiPointProof<E>, SolvencyError> {
+        prover_key.multi_point_open(polynomials, commitments, point_sets, transcript)
+    }
+
+    fn batch_verify(
+        verifier_key: &VerifierKey<E>,
+        commitments: &[E::G1],
+        point_sets: &[Vec<E::ScalarField>],
+        values: &[Vec<E::ScalarField>],
+        transcript: &mut Transcript,
+        proof: &MultiPointProof<E>,
+    ) -> bool {
+        let values: Vec<Vec<Evaluation<E>>> = values
+            .iter()
+            .map(|set| set.iter().map(|y| Evaluation::Scalar(*y)).collect())
+            .collect();
+        verifier_key.verify_multi_point_open(commitments, point_sets, &values, transcript, proof)
+    }
+}
@@ -1,16 +1,122 @@
-use ark_bn254::Fr;
 use ark_ec::pairing::Pairing;
+use ark_ff::{Field, PrimeField};
 use ark_std::Zero;
-use serde_json::Error;
+
+use crate::error::SolvencyError;
 
 #[derive(Debug, Clone)]
 pub struct User {
     pub username: u32,
-    pub balance: u32,
+    // in the smallest unit of the currency
+    pub balance: u64,
     pub salt: u32,
 }
 
-pub fn generate_witness<E: Pairing>(users: Vec<User>) -> Result<(Vec<E::ScalarField>, Vec<E::ScalarField>), Error> {
+/// Witness tables for P(X) and I(X), in evaluation form over the domain
+pub type Witness<F> = (Vec<F>, Vec<F>);
+
+/// Where the cells of each user sit in I(X). Every user owns `row_width` consecutive rows: the
+/// binary decomposition of their balance over `bits + 1` rows, ending with the balance at
+/// `balance_offset`, and the running total at `total_offset`. Other rows are zeroes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WitnessLayout {
+    pub bits: usize,
+    pub row_width: usize,
+    pub balance_offset: usize,
+    pub total_offset: usize,
+}
+
+impl Default for WitnessLayout {
+    // The layout of the proof of solvency write-up: 14 bits in a 16 rows block
+    fn default() -> Self {
+        Self {
+            bits: 14,
+            row_width: 16,
+            balance_offset: 14,
+            total_offset: 15,
+        }
+    }
+}
+
+impl WitnessLayout {
+    /// Checks that the cells fit in the rows without overlapping, and that balances fit in a u64
+    pub fn validate(&self) -> Result<(), SolvencyError> {
+        if self.bits == 0 || self.bits > 64 {
+            return Err(SolvencyError::InvalidLayout(format!(
+                "{} bits do not fit a u64 balance",
+                self.bits
+            )));
+        }
+        if self.bits > self.balance_offset {
+            return Err(SolvencyError::InvalidLayout(format!(
+                "the {} bits do not fit before the balance at offset {}",
+                self.bits, self.balance_offset
+            )));
+        }
+        if self.balance_offset >= self.row_width || self.total_offset >= self.row_width {
+            return Err(SolvencyError::InvalidLayout(format!(
+                "offsets must be less than the row width {}",
+                self.row_width
+            )));
+        }
+        let cells = self.decomposition_offset()..=self.balance_offset;
+        if cells.contains(&self.total_offset) {
+            return Err(SolvencyError::InvalidLayout(format!(
+                "the running total at offset {} overlaps the balance cells",
+                self.total_offset
+            )));
+        }
+        Ok(())
+    }
+
+    /// Offset of the first cell of the decomposition, which is always zero
+    pub fn decomposition_offset(&self) -> usize {
+        self.balance_offset - self.bits
+    }
+
+    pub fn balance_row(&self, user: usize) -> usize {
+        self.row_width * user + self.balance_offset
+    }
+
+    pub fn total_row(&self, user: usize) -> usize {
+        self.row_width * user + self.total_offset
+    }
+
+    /// Whether `balance` can be written with the bits of the layout
+    pub fn fits(&self, balance: u64) -> bool {
+        balance.checked_shr(self.bits as u32).unwrap_or(0) == 0
+    }
+}
+
+/// Sum of the balances of the users, which the exchange declares as its total liabilities
+pub fn total_balance(users: &[User]) -> u128 {
+    users.iter().map(|user| user.balance as u128).sum()
+}
+
+/// Average balance T / n in the scalar field, exact even when n does not divide the declared
+/// total T. There is no average of zero users.
+pub fn average_balance<F: PrimeField>(
+    declared_total: u128,
+    n_users: usize,
+) -> Result<F, SolvencyError> {
+    let inverse = F::from(n_users as u64)
+        .inverse()
+        .ok_or(SolvencyError::EmptyUsers)?;
+    Ok(F::from(declared_total) * inverse)
+}
+
+/// Witness tables of the users for the `declared_total` of their balances. The running total of
+/// the last user is zero iff the balances sum up to `declared_total`.
+pub fn generate_witness<E: Pairing>(
+    users: Vec<User>,
+    declared_total: u128,
+    layout: &WitnessLayout,
+) -> Result<Witness<E::ScalarField>, SolvencyError> {
+    layout.validate()?;
+    if users.is_empty() {
+        return Err(SolvencyError::EmptyUsers);
+    }
+
     let mut p_witness = Vec::new();
     let mut i_witness = Vec::new();
     
@@ -19,43 +125,43 @@ pub fn generate_witness<E: Pairing>(users: Vec<User>) -> Result<(Vec<E::ScalarFi
     // For each user
     // 1. Push the username + salt to the p_witness
     // 2. Push the balance to the p_witness
-    // 3. Create an array of 16 elements
-    // 4. Take the user balance and assign it to the 14th index of the array
-    // 5. Divide the balance by 2 and assign to the previous index of the array
-    // 6. Repeat until it goes to 1
-    // 7. Pad the rest of the array with 0s
-    // 8. Assign the running total with an offset so that it sums to zero only if the total matches the declared total to the 15th index
-    let mut accumlated_balance = 0;
+    // 3. Create an array of `row_width` elements, the decomposition starting with 0
+    // 4. Decompose the balance in `bits` bits, most significant first
+    // 5. Each following cell is twice the previous one plus the next bit:
+    //    I(ω^(i+1)) = 2 * I(ω^i) + bit
+    // 6. After the last bit, the cell at `balance_offset` holds the balance
+    // 7. Assign the running total, offset by the average of the declared total, to the cell at
+    //    `total_offset`. It is computed in the field, where negative totals are field negations.
 
     // range over users. Add to index i of p_wintess -> (username + salt) and to index i + 1 -> balance.
-    for user in users.clone() {
-        p_witness.push(E::ScalarField::from(user.username + user.salt));
+    for (i, user) in users.iter().enumerate() {
+        if !layout.fits(user.balance) {
+            return Err(SolvencyError::InvalidBalance {
+                user: i,
+                balance: user.balance,
+            });
+        }
+        p_witness.push(E::ScalarField::from(user.username) + E::ScalarField::from(user.salt));
         p_witness.push(E::ScalarField::from(user.balance));
-        accumlated_balance += user.balance;
     }
 
-    let avg = accumlated_balance / users.len() as u32;
-
-    let mut running_total = 0;
+    let average = average_balance::<E::ScalarField>(declared_total, users.len())?;
+    let mut running_total = E::ScalarField::zero();
 
     for user in users {
-        let mut user_array = [E::ScalarField::zero(); 16];
-        let mut balance = user.balance as u32;
-        let mut index = 14;
-        user_array[index] = E::ScalarField::from(balance);
-        while balance > 0 {
-            index -= 1;
-            user_array[index] = E::ScalarField::from(balance / 2);
-            balance /= 2;
+        let mut user_array = vec![E::ScalarField::zero(); layout.row_width];
+        let start = layout.decomposition_offset();
+        let mut cell = E::ScalarField::zero();
+        for (index, shift) in (0..layout.bits).rev().enumerate() {
+            cell = cell.double() + E::ScalarField::from((user.balance >> shift) & 1);
+            user_array[start + index + 1] = cell;
         }
 
-        running_total = running_total + user.balance as i32 - avg as i32;
+        running_total += E::ScalarField::from(user.balance) - average;
 
-        user_array[15] = E::ScalarField::from(running_total as u32);
+        user_array[layout.total_offset] = running_total;
 
-        for i in 0..16 {
-            i_witness.push(E::ScalarField::from(user_array[i]));
-        }
+        i_witness.extend_from_slice(&user_array);
     }
 
     // fill p_witness with zeroes to make it the same length as i_witness
@@ -63,7 +169,70 @@ pub fn generate_witness<E: Pairing>(users: Vec<User>) -> Result<(Vec<E::ScalarFi
         p_witness.push(E::ScalarField::zero());
     }
 
-    assert!(p_witness.len() == i_witness.len());
-
     Ok((p_witness, i_witness))
 }
+
+/// Checks the `bits + 1` cells of a user's row from the start of the decomposition, as opened
+/// from I(X): they start from 0 and each of them is twice the previous one plus a bit, so that
+/// the last one, which must be `balance`, fits in the bits of the layout
+pub fn verify_balance_cells<F: PrimeField>(
+    cells: &[F],
+    balance: u64,
+    layout: &WitnessLayout,
+) -> Result<(), SolvencyError> {
+    let n_cells = layout.bits + 1;
+    if cells.len() != n_cells {
+        return Err(SolvencyError::LengthMismatch {
+            expected: n_cells,
+            found: cells.len(),
+        });
+    }
+    if !cells[0].is_zero() {
+        return Err(SolvencyError::UnsatisfiedConstraint {
+            gate: "first cell",
+            row: 0,
+        });
+    }
+    for (row, pair) in cells.windows(2).enumerate() {
+        let bit = pair[1] - pair[0].double();
+        if !bit.is_zero() && !bit.is_one() {
+            return Err(SolvencyError::UnsatisfiedConstraint { gate: "bits", row });
+        }
+    }
+    if cells[n_cells - 1] != F::from(balance) {
+        return Err(SolvencyError::UnsatisfiedConstraint {
+            gate: "balance",
+            row: n_cells - 1,
+        });
+    }
+    Ok(())
+}
+
+/// Rows that differ between two witnesses, as (row, new value - old value), for P(X) and I(X)
+pub type WitnessDiff<F> = (Vec<(usize, F)>, Vec<(usize, F)>);
+
+/// Diff of the witnesses of two user lists and their declared totals, to update commitments in
+/// evaluation form. Rows missing from the shorter witness are zeroes. Note that the running total
+/// of I(X) is offset by the average of the declared total, so a single balance change also
+/// changes the total rows of all users.
+pub fn diff_witness<E: Pairing>(
+    old_users: Vec<User>,
+    old_total: u128,
+    new_users: Vec<User>,
+    new_total: u128,
+    layout: &WitnessLayout,
+) -> Result<WitnessDiff<E::ScalarField>, SolvencyError> {
+    let (old_p, old_i) = generate_witness::<E>(old_users, old_total, layout)?;
+    let (new_p, new_i) = generate_witness::<E>(new_users, new_total, layout)?;
+    Ok((diff_rows(&old_p, &new_p), diff_rows(&old_i, &new_i)))
+}
+
+fn diff_rows<F: Field>(old: &[F], new: &[F]) -> Vec<(usize, F)> {
+    let zero = F::zero();
+    (0..old.len().max(new.len()))
+        .filter_map(|row| {
+            let delta = *new.get(row).unwrap_or(&zero) - old.get(row).unwrap_or(&zero);
+            (!delta.is_zero()).then_some((row, delta))
+        })
+        .collect()
+}
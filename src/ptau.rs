@@ -0,0 +1,176 @@
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use ark_bn254::{Bn254, Fq, Fq2, G1Affine, G2Affine};
+use ark_ff::{BigInt, PrimeField};
+
+use crate::error::SolvencyError;
+use crate::kzg::KZG;
+
+// Layout of the snarkjs .ptau files, see https://github.com/iden3/snarkjs/blob/master/src/powersoftau_new.js
+// The file starts with the magic, a version and the number of sections. Each section is then a
+// section type (u32), a byte size (u64) and its content. All integers are little endian and all
+// field elements are written in Montgomery form.
+const PTAU_MAGIC: &[u8; 4] = b"ptau";
+const SECTION_HEADER: u32 = 1;
+const SECTION_TAU_G1: u32 = 2;
+const SECTION_TAU_G2: u32 = 3;
+const FQ_BYTES: usize = 32;
+
+/// Reads the SRS for a polynomial of degree `degree` from a snarkjs Powers-of-Tau file over BN254.
+/// Only the powers needed are read, the rest of the file is skipped. Distributed files such as
+/// `powersOfTau28_hez_final_12.ptau` are cut down from a larger ceremony, whose power is recorded
+/// in the header: the SRS records the degree of the largest power of the ceremony, not of the
+/// file, as its `published_degree`. Such an SRS commits, opens and multi-opens, but can not prove
+/// or check degree bounds, see [`KZG::degree_bound_key`].
+pub fn load_ptau<P: AsRef<Path>>(path: P, degree: usize) -> Result<KZG<Bn254>, SolvencyError> {
+    let file = File::open(path)?;
+    read_ptau(BufReader::new(file), degree)
+}
+
+pub fn read_ptau<R: Read + Seek>(mut reader: R, degree: usize) -> Result<KZG<Bn254>, SolvencyError> {
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != PTAU_MAGIC {
+        return Err(SolvencyError::InvalidSrs("not a ptau file".to_string()));
+    }
+    let _version = read_u32(&mut reader)?;
+    let n_sections = read_u32(&mut reader)?;
+
+    // record where each section starts, sections can come in any order
+    let mut sections = vec![];
+    for _ in 0..n_sections {
+        let section_type = read_u32(&mut reader)?;
+        let size = read_u64(&mut reader)?;
+        let start = reader.stream_position()?;
+        sections.push((section_type, start, size));
+        reader.seek(SeekFrom::Start(start + size))?;
+    }
+    let find_section = |section_type: u32| {
+        sections
+            .iter()
+            .find(|(t, _, _)| *t == section_type)
+            .map(|(_, start, size)| (*start, *size))
+            .ok_or_else(|| {
+                SolvencyError::InvalidSrs(format!("missing section {} in ptau file", section_type))
+            })
+    };
+
+    // header: field element size, base field modulus, power of the file and power of the ceremony
+    // it was cut down from
+    let (start, _) = find_section(SECTION_HEADER)?;
+    reader.seek(SeekFrom::Start(start))?;
+    let n8 = read_u32(&mut reader)? as usize;
+    if n8 != FQ_BYTES {
+        return Err(SolvencyError::InvalidSrs(format!(
+            "field elements of {} bytes, expected {}",
+            n8, FQ_BYTES
+        )));
+    }
+    if read_bigint(&mut reader)? != Fq::MODULUS {
+        return Err(SolvencyError::InvalidSrs(
+            "ptau file is not over the BN254 base field".to_string(),
+        ));
+    }
+    let power = read_u32(&mut reader)?;
+    let ceremony_power = read_u32(&mut reader)?;
+    if power >= 32 {
+        return Err(SolvencyError::InvalidSrs(format!("invalid power {}", power)));
+    }
+    if ceremony_power < power || ceremony_power >= 32 {
+        return Err(SolvencyError::InvalidSrs(format!(
+            "invalid ceremony power {} for a file of power {}",
+            ceremony_power, power
+        )));
+    }
+    // a transcript of power k holds 2^(k+1) - 1 powers in G1 and 2^k in G2
+    let n_g1 = (1usize << (power + 1)) - 1;
+    let n_g2 = 1usize << power;
+    if degree + 1 > n_g1 {
+        return Err(SolvencyError::DegreeTooLarge {
+            degree,
+            max_degree: n_g1 - 1,
+        });
+    }
+
+    let (start, size) = find_section(SECTION_TAU_G1)?;
+    if size < (n_g1 * 2 * FQ_BYTES) as u64 {
+        return Err(SolvencyError::InvalidSrs("tau G1 section is too small".to_string()));
+    }
+    reader.seek(SeekFrom::Start(start))?;
+    let mut crs = Vec::with_capacity(degree + 1);
+    for i in 0..degree + 1 {
+        crs.push(read_g1(&mut reader, i)?);
+    }
+
+    // G2 powers are only used by multi-openings, we keep as many as the degree allows
+    let (start, size) = find_section(SECTION_TAU_G2)?;
+    if size < (n_g2 * 4 * FQ_BYTES) as u64 {
+        return Err(SolvencyError::InvalidSrs("tau G2 section is too small".to_string()));
+    }
+    reader.seek(SeekFrom::Start(start))?;
+    let mut crs_2 = Vec::with_capacity(n_g2.min(degree + 1));
+    for i in 0..n_g2.min(degree + 1) {
+        crs_2.push(read_g2(&mut reader, i)?);
+    }
+
+    // degree bounds are checked against the largest power of the whole ceremony, which anyone
+    // holding its transcript has, not against the largest power of the file or the one read
+    let mut kzg = KZG::from_powers(crs, crs_2)?;
+    kzg.published_degree = (1usize << (ceremony_power + 1)) - 2;
+    Ok(kzg)
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> Result<u32, SolvencyError> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> Result<u64, SolvencyError> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_bigint<R: Read>(reader: &mut R) -> Result<BigInt<4>, SolvencyError> {
+    let mut limbs = [0u64; 4];
+    for limb in limbs.iter_mut() {
+        *limb = read_u64(reader)?;
+    }
+    Ok(BigInt(limbs))
+}
+
+fn invalid_point(group: &str, index: usize) -> SolvencyError {
+    SolvencyError::InvalidSrs(format!("invalid point encoding for tau_{}[{}]", group, index))
+}
+
+// Reads a base field element in Montgomery form, rejecting non canonical encodings
+fn read_fq<R: Read>(reader: &mut R, group: &str, index: usize) -> Result<Fq, SolvencyError> {
+    let repr = read_bigint(reader)?;
+    if repr >= Fq::MODULUS {
+        return Err(invalid_point(group, index));
+    }
+    Ok(Fq::new_unchecked(repr))
+}
+
+fn read_g1<R: Read>(reader: &mut R, index: usize) -> Result<G1Affine, SolvencyError> {
+    let x = read_fq(reader, "g1", index)?;
+    let y = read_fq(reader, "g1", index)?;
+    let point = G1Affine::new_unchecked(x, y);
+    if !point.is_on_curve() || !point.is_in_correct_subgroup_assuming_on_curve() {
+        return Err(invalid_point("g1", index));
+    }
+    Ok(point)
+}
+
+fn read_g2<R: Read>(reader: &mut R, index: usize) -> Result<G2Affine, SolvencyError> {
+    let x = Fq2::new(read_fq(reader, "g2", index)?, read_fq(reader, "g2", index)?);
+    let y = Fq2::new(read_fq(reader, "g2", index)?, read_fq(reader, "g2", index)?);
+    let point = G2Affine::new_unchecked(x, y);
+    if !point.is_on_curve() || !point.is_in_correct_subgroup_assuming_on_curve() {
+        return Err(invalid_point("g2", index));
+    }
+    Ok(point)
+}
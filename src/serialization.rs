@@ -0,0 +1,242 @@
+use std::fs;
+use std::path::Path;
+
+use ark_bls12_381::Bls12_381;
+use ark_bn254::Bn254;
+use ark_ec::pairing::Pairing;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Compress, Validate};
+use serde::{Deserialize, Serialize};
+
+use crate::error::SolvencyError;
+
+// Binary layout of every object written by the crate:
+// magic (4 bytes) | format version (u16 LE) | curve id length (u8) | curve id | kind (u8) |
+// compressed flag (u8) | canonical arkworks serialization of the object
+const MAGIC: &[u8; 4] = b"KZGS";
+// Version 2 records the published degree of the SRS in KZG objects
+pub const FORMAT_VERSION: u16 = 2;
+
+/// Identifies the curve an object was serialized over, so that it is not read over another one
+pub trait CurveId: Pairing {
+    const CURVE_ID: &'static str;
+}
+
+impl CurveId for Bn254 {
+    const CURVE_ID: &'static str = "bn254";
+}
+
+impl CurveId for Bls12_381 {
+    const CURVE_ID: &'static str = "bls12-381";
+}
+
+/// What a serialized object is, so that e.g. a proof can not be read as a commitment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectKind {
+    Srs,
+    ProverKey,
+    VerifierKey,
+    Commitment,
+    OpeningProof,
+    CeremonyPowers,
+    CeremonyContribution,
+}
+
+impl ObjectKind {
+    const ALL: [ObjectKind; 7] = [
+        ObjectKind::Srs,
+        ObjectKind::ProverKey,
+        ObjectKind::VerifierKey,
+        ObjectKind::Commitment,
+        ObjectKind::OpeningProof,
+        ObjectKind::CeremonyPowers,
+        ObjectKind::CeremonyContribution,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ObjectKind::Srs => "srs",
+            ObjectKind::ProverKey => "prover_key",
+            ObjectKind::VerifierKey => "verifier_key",
+            ObjectKind::Commitment => "commitment",
+            ObjectKind::OpeningProof => "opening_proof",
+            ObjectKind::CeremonyPowers => "ceremony_powers",
+            ObjectKind::CeremonyContribution => "ceremony_contribution",
+        }
+    }
+
+    fn tag(&self) -> u8 {
+        Self::ALL.iter().position(|kind| kind == self).unwrap() as u8
+    }
+}
+
+/// JSON representation of a serialized object. `data` is the hex encoded canonical serialization.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JsonEnvelope {
+    pub version: u16,
+    pub curve: String,
+    pub kind: String,
+    pub compressed: bool,
+    pub data: String,
+}
+
+pub fn to_bytes<E: CurveId, T: CanonicalSerialize>(
+    kind: ObjectKind,
+    value: &T,
+    compress: Compress,
+) -> Result<Vec<u8>, SolvencyError> {
+    let mut bytes = MAGIC.to_vec();
+    bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    bytes.push(E::CURVE_ID.len() as u8);
+    bytes.extend_from_slice(E::CURVE_ID.as_bytes());
+    bytes.push(kind.tag());
+    bytes.push(u8::from(compress == Compress::Yes));
+    value.serialize_with_mode(&mut bytes, compress)?;
+    Ok(bytes)
+}
+
+/// Reads an object written by `to_bytes`. Points are checked to be on the curve and in the
+/// right subgroup.
+pub fn from_bytes<E: CurveId, T: CanonicalDeserialize>(
+    kind: ObjectKind,
+    bytes: &[u8],
+) -> Result<T, SolvencyError> {
+    let header_error = |reason: &str| SolvencyError::Serialization(reason.to_string());
+    if bytes.len() < MAGIC.len() + 3 || &bytes[..MAGIC.len()] != MAGIC {
+        return Err(header_error("missing magic bytes"));
+    }
+    let mut offset = MAGIC.len();
+    let version = u16::from_le_bytes([bytes[offset], bytes[offset + 1]]);
+    if version != FORMAT_VERSION {
+        return Err(SolvencyError::Serialization(format!(
+            "unsupported format version {}",
+            version
+        )));
+    }
+    offset += 2;
+    let curve_len = bytes[offset] as usize;
+    offset += 1;
+    if bytes.len() < offset + curve_len + 2 {
+        return Err(header_error("truncated header"));
+    }
+    let curve = &bytes[offset..offset + curve_len];
+    if curve != E::CURVE_ID.as_bytes() {
+        return Err(SolvencyError::Serialization(format!(
+            "object is over {}, expected {}",
+            String::from_utf8_lossy(curve),
+            E::CURVE_ID
+        )));
+    }
+    offset += curve_len;
+    if bytes[offset] != kind.tag() {
+        return Err(SolvencyError::Serialization(format!(
+            "object is not a {}",
+            kind.name()
+        )));
+    }
+    let compress = match bytes[offset + 1] {
+        0 => Compress::No,
+        1 => Compress::Yes,
+        _ => return Err(header_error("invalid compression flag")),
+    };
+    offset += 2;
+    let mut payload = &bytes[offset..];
+    let value = T::deserialize_with_mode(&mut payload, compress, Validate::Yes)?;
+    if !payload.is_empty() {
+        return Err(header_error("trailing bytes after the object"));
+    }
+    Ok(value)
+}
+
+pub fn to_json<E: CurveId, T: CanonicalSerialize>(
+    kind: ObjectKind,
+    value: &T,
+    compress: Compress,
+) -> Result<String, SolvencyError> {
+    let mut data = vec![];
+    value.serialize_with_mode(&mut data, compress)?;
+    let envelope = JsonEnvelope {
+        version: FORMAT_VERSION,
+        curve: E::CURVE_ID.to_string(),
+        kind: kind.name().to_string(),
+        compressed: compress == Compress::Yes,
+        data: to_hex(&data),
+    };
+    serde_json::to_string_pretty(&envelope)
+        .map_err(|err| SolvencyError::Serialization(err.to_string()))
+}
+
+pub fn from_json<E: CurveId, T: CanonicalDeserialize>(
+    kind: ObjectKind,
+    json: &str,
+) -> Result<T, SolvencyError> {
+    let envelope: JsonEnvelope = serde_json::from_str(json)
+        .map_err(|err| SolvencyError::Serialization(err.to_string()))?;
+    if envelope.version != FORMAT_VERSION {
+        return Err(SolvencyError::Serialization(format!(
+            "unsupported format version {}",
+            envelope.version
+        )));
+    }
+    if envelope.curve != E::CURVE_ID {
+        return Err(SolvencyError::Serialization(format!(
+            "object is over {}, expected {}",
+            envelope.curve,
+            E::CURVE_ID
+        )));
+    }
+    if envelope.kind != kind.name() {
+        return Err(SolvencyError::Serialization(format!(
+            "object is a {}, expected a {}",
+            envelope.kind,
+            kind.name()
+        )));
+    }
+    let compress = if envelope.compressed {
+        Compress::Yes
+    } else {
+        Compress::No
+    };
+    let data = from_hex(&envelope.data)?;
+    let mut payload = &data[..];
+    let value = T::deserialize_with_mode(&mut payload, compress, Validate::Yes)?;
+    if !payload.is_empty() {
+        return Err(SolvencyError::Serialization(
+            "trailing bytes after the object".to_string(),
+        ));
+    }
+    Ok(value)
+}
+
+/// Writes an object with `to_bytes`, compressed
+pub fn write_to_file<E: CurveId, T: CanonicalSerialize, P: AsRef<Path>>(
+    kind: ObjectKind,
+    value: &T,
+    path: P,
+) -> Result<(), SolvencyError> {
+    fs::write(path, to_bytes::<E, T>(kind, value, Compress::Yes)?)?;
+    Ok(())
+}
+
+pub fn read_from_file<E: CurveId, T: CanonicalDeserialize, P: AsRef<Path>>(
+    kind: ObjectKind,
+    path: P,
+) -> Result<T, SolvencyError> {
+    from_bytes::<E, T>(kind, &fs::read(path)?)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn from_hex(hex: &str) -> Result<Vec<u8>, SolvencyError> {
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return Err(SolvencyError::Serialization("invalid hex string".to_string()));
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&hex[i..i + 2], 16)
+                .map_err(|_| SolvencyError::Serialization("invalid hex string".to_string()))
+        })
+        .collect()
+}
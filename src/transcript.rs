@@ -0,0 +1,60 @@
+use ark_ff::PrimeField;
+use ark_serialize::CanonicalSerialize;
+use sha2::{Digest, Sha256};
+
+/// Fiat-Shamir transcript: everything the prover sends is appended to it, and the verifier
+/// challenges are squeezed out of it, so that proofs are non-interactive. The batched openings of
+/// `kzg` and `ipa` and the proofs of `constraints` append the commitments, points, values and
+/// public inputs they are given before squeezing any challenge, so callers do not append them
+/// again. Callers only start the prover and the verifier from transcripts in the same state, e.g.
+/// fresh ones with the same label.
+#[derive(Debug, Clone)]
+pub struct Transcript {
+    hasher: Sha256,
+}
+
+impl Transcript {
+    /// Starts a transcript for the protocol named `label`, so that challenges of different
+    /// protocols never collide
+    pub fn new(label: &'static [u8]) -> Self {
+        let mut transcript = Self {
+            hasher: Sha256::new(),
+        };
+        transcript.append_message(b"domain", label);
+        transcript
+    }
+
+    // Labels and messages are length prefixed, so that different sequences of messages can not
+    // hash the same
+    pub fn append_message(&mut self, label: &'static [u8], message: &[u8]) {
+        self.hasher.update((label.len() as u64).to_le_bytes());
+        self.hasher.update(label);
+        self.hasher.update((message.len() as u64).to_le_bytes());
+        self.hasher.update(message);
+    }
+
+    /// Appends a field element, a point of G1 or G2, or any value of the crate, in its compressed
+    /// canonical serialization
+    pub fn append<T: CanonicalSerialize + ?Sized>(&mut self, label: &'static [u8], value: &T) {
+        let mut bytes = vec![];
+        value
+            .serialize_compressed(&mut bytes)
+            .expect("serializing into a vector can not fail");
+        self.append_message(label, &bytes);
+    }
+
+    /// Challenge out of everything appended so far. 64 bytes are reduced modulo the field order
+    /// so that the challenge is close to uniform, and they are appended back so that the next
+    /// challenge differs.
+    pub fn challenge_scalar<F: PrimeField>(&mut self, label: &'static [u8]) -> F {
+        self.append_message(b"challenge", label);
+        let mut bytes = Vec::with_capacity(64);
+        for counter in 0u8..2 {
+            let mut hasher = self.hasher.clone();
+            hasher.update([counter]);
+            bytes.extend_from_slice(&hasher.finalize());
+        }
+        self.append_message(b"challenge_bytes", &bytes);
+        F::from_le_bytes_mod_order(&bytes)
+    }
+}
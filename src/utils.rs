@@ -1,22 +1,28 @@
 use ark_ec::pairing::Pairing;
-use ark_ff::Field;
+use ark_ff::{FftField, Field, Zero};
 use ark_poly::univariate::DensePolynomial;
 use ark_poly::{EvaluationDomain, GeneralEvaluationDomain, Polynomial, DenseUVPolynomial};
 use ark_std::rand::Rng;
 use ark_std::UniformRand;
 
+use crate::error::SolvencyError;
+
+/// An evaluation domain together with its elements, in order
+pub type OmegaDomain<F> = (GeneralEvaluationDomain<F>, Vec<F>);
+
 pub fn get_omega_domain<E: Pairing>(
     n: usize,
-) -> (GeneralEvaluationDomain<E::ScalarField>, Vec<E::ScalarField>) {
+) -> Result<OmegaDomain<E::ScalarField>, SolvencyError> {
     // Builds the domain consisting of n roots of unity in F
-    let omegas = GeneralEvaluationDomain::<E::ScalarField>::new(n).unwrap();
+    let omegas = GeneralEvaluationDomain::<E::ScalarField>::new(n)
+        .ok_or(SolvencyError::InvalidDomainSize(n))?;
 
     // Our omegas (domain of P(X)) in a vector that we will access later on
     let mut domain_elements: Vec<E::ScalarField> = vec![];
     for element in omegas.elements() {
         domain_elements.push(element);
     }
-    (omegas, domain_elements)
+    Ok((omegas, domain_elements))
 }
 
 pub fn generate_random_scalar_field_elements<E: Pairing>(
@@ -35,7 +41,7 @@ pub fn generate_random_scalar_field_elements<E: Pairing>(
 
 pub fn compute_evaluations_for_specific_omegas<E: Pairing>(
     omegas_indexes: Vec<usize>,
-    omega_elements: &Vec<E::ScalarField>,
+    omega_elements: &[E::ScalarField],
     p: &DensePolynomial<E::ScalarField>,
 ) -> Vec<<E as Pairing>::ScalarField> {
     // computes the evaluations of P(w^i) at specific indexes and stores them in a vector.
@@ -44,7 +50,7 @@ pub fn compute_evaluations_for_specific_omegas<E: Pairing>(
     for (i, element) in omega_elements.iter().enumerate() {
         if omegas_indexes.contains(&i) {
             // at omega^2 and omega^3, we want to have P(omega^2) and P(omega^3)
-            let eval = p.evaluate(&element);
+            let eval = p.evaluate(element);
             evaluations.push(eval);
         } else {
             evaluations.push(E::ScalarField::ZERO);
@@ -53,18 +59,166 @@ pub fn compute_evaluations_for_specific_omegas<E: Pairing>(
     evaluations
 }
 
-pub fn build_zero_polynomial<E: Pairing>(roots: &Vec<E::ScalarField>) -> DensePolynomial<<E as Pairing>::ScalarField> {
+pub fn build_zero_polynomial<E: Pairing>(
+    roots: &[E::ScalarField],
+) -> Result<DensePolynomial<<E as Pairing>::ScalarField>, SolvencyError> {
     // roots are the values at which the polynomial will be zero
     // (X - roots[0]) * (X - roots[1]) * ... * (X - roots[n])
-    let mut polys = vec![];
-    for root in roots {
-        let poly = DensePolynomial::from_coefficients_vec(vec![*root * (-E::ScalarField::ONE), E::ScalarField::ONE]);
-        polys.push(poly);
-    }
-    // multiply all the different polys together to get one single polynomial
-    let mut zero_poly = polys[0].clone();
-    for i in 1..polys.len() {
-        zero_poly = &zero_poly * &polys[i];
-    }
-    zero_poly
-}
\ No newline at end of file
+    if roots.is_empty() {
+        return Err(SolvencyError::EmptyRoots);
+    }
+    if let Some(coset) = as_coset(roots) {
+        return Ok(coset.vanishing_polynomial().into());
+    }
+    // product tree: the linear factors are multiplied pairwise, level by level, so that every
+    // product is between polynomials of about the same degree and goes through an FFT
+    let mut layer: Vec<DensePolynomial<E::ScalarField>> = roots
+        .iter()
+        .map(|root| DensePolynomial::from_coefficients_vec(vec![-*root, E::ScalarField::ONE]))
+        .collect();
+    while layer.len() > 1 {
+        layer = layer
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => left * right,
+                _ => pair[0].clone(),
+            })
+            .collect();
+    }
+    Ok(layer.swap_remove(0))
+}
+
+pub fn interpolate_points<E: Pairing>(
+    points: &[E::ScalarField],
+    values: &[E::ScalarField],
+) -> Result<DensePolynomial<E::ScalarField>, SolvencyError> {
+    // lowest degree polynomial going through (points[i], values[i]), built as the sum of
+    // values[i] * prod_{j != i} (X - points[j]) / (points[i] - points[j])
+    if points.is_empty() {
+        return Err(SolvencyError::EmptyRoots);
+    }
+    if points.len() != values.len() {
+        return Err(SolvencyError::LengthMismatch {
+            expected: points.len(),
+            found: values.len(),
+        });
+    }
+    if let Some(coset) = as_coset(points) {
+        return Ok(DensePolynomial::from_coefficients_vec(coset.ifft(values)));
+    }
+    // Z(X) = prod (X - points[j]) is built once, and each prod_{j != i} (X - points[j]) is
+    // Z(X) / (X - points[i]), so that the interpolation costs O(k^2) for k points
+    let zero_polynomial = build_zero_polynomial::<E>(points)?;
+    let mut coeffs = vec![E::ScalarField::ZERO; points.len()];
+    for (point, value) in points.iter().zip(values) {
+        let basis = divide_by_root(&zero_polynomial, *point);
+        let denominator = basis.evaluate(point);
+        let scale = *value * denominator.inverse().ok_or(SolvencyError::DuplicatePoints)?;
+        for (coeff, basis_coeff) in coeffs.iter_mut().zip(&basis.coeffs) {
+            *coeff += scale * basis_coeff;
+        }
+    }
+    Ok(DensePolynomial::from_coefficients_vec(coeffs))
+}
+
+/// Evaluations of `polynomial` at `points`. When the points are the rows of a coset, as when
+/// opening every user's row, they come out of a single FFT rather than one evaluation per point.
+pub fn evaluate_points<E: Pairing>(
+    polynomial: &DensePolynomial<E::ScalarField>,
+    points: &[E::ScalarField],
+) -> Vec<E::ScalarField> {
+    let Some(coset) = as_coset(points) else {
+        return points.iter().map(|x| polynomial.evaluate(x)).collect();
+    };
+    // the polynomial reduced modulo X^k - c^k agrees with it over the coset
+    let shift = coset.coset_offset_pow_size();
+    let mut reduced = vec![E::ScalarField::ZERO; points.len()];
+    let mut power = E::ScalarField::ONE;
+    for chunk in polynomial.coeffs.chunks(points.len()) {
+        for (reduced, coeff) in reduced.iter_mut().zip(chunk) {
+            *reduced += power * coeff;
+        }
+        power *= shift;
+    }
+    coset.fft(&reduced)
+}
+
+/// Quotient of `polynomial` by a monic `zero_polynomial`, such as the ones of
+/// [`build_zero_polynomial`]. Only the non-zero coefficients of the divisor are used, so that
+/// dividing by the X^k - c of a coset costs O(deg) rather than O(deg * k).
+pub fn divide_by_zero_polynomial<E: Pairing>(
+    polynomial: &DensePolynomial<E::ScalarField>,
+    zero_polynomial: &DensePolynomial<E::ScalarField>,
+) -> DensePolynomial<E::ScalarField> {
+    let k = zero_polynomial.degree();
+    if polynomial.coeffs.len() <= k {
+        return DensePolynomial::from_coefficients_vec(vec![]);
+    }
+    let terms: Vec<(usize, E::ScalarField)> = zero_polynomial.coeffs[..k]
+        .iter()
+        .enumerate()
+        .filter(|(_, coeff)| !coeff.is_zero())
+        .map(|(i, coeff)| (i, *coeff))
+        .collect();
+    let mut remainder = polynomial.coeffs.clone();
+    let mut quotient = vec![E::ScalarField::ZERO; remainder.len() - k];
+    for d in (0..quotient.len()).rev() {
+        let q = remainder[d + k];
+        quotient[d] = q;
+        for (i, coeff) in &terms {
+            remainder[d + i] -= q * coeff;
+        }
+    }
+    DensePolynomial::from_coefficients_vec(quotient)
+}
+
+// Z(X) / (X - root) by synthetic division, for a root of Z(X)
+fn divide_by_root<F: Field>(polynomial: &DensePolynomial<F>, root: F) -> DensePolynomial<F> {
+    let mut quotient = vec![F::ZERO; polynomial.coeffs.len().saturating_sub(1)];
+    let mut carry = F::ZERO;
+    for i in (0..quotient.len()).rev() {
+        carry = polynomial.coeffs[i + 1] + carry * root;
+        quotient[i] = carry;
+    }
+    DensePolynomial::from_coefficients_vec(quotient)
+}
+
+// The points as the coset c<g> of a subgroup of their size, listed in the order c, cg, cg^2...
+// This is how rows of a domain are opened, and lets interpolation go through an FFT.
+fn as_coset<F: FftField>(points: &[F]) -> Option<GeneralEvaluationDomain<F>> {
+    let offset = *points.first()?;
+    if offset.is_zero() {
+        return None;
+    }
+    let coset = GeneralEvaluationDomain::<F>::new(points.len())
+        .filter(|domain| domain.size() == points.len())?
+        .get_coset(offset)?;
+    coset.elements().eq(points.iter().copied()).then_some(coset)
+}
+
+/// Evaluations at `z` of the Lagrange basis polynomials of `points`, so that the interpolant of
+/// `values` over `points` evaluates at `z` to sum values[i] * coefficients[i]
+pub fn lagrange_coefficients<E: Pairing>(
+    points: &[E::ScalarField],
+    z: E::ScalarField,
+) -> Result<Vec<E::ScalarField>, SolvencyError> {
+    if points.is_empty() {
+        return Err(SolvencyError::EmptyRoots);
+    }
+    if let Some(coset) = as_coset(points) {
+        return Ok(coset.evaluate_all_lagrange_coefficients(z));
+    }
+    let mut coefficients = Vec::with_capacity(points.len());
+    for (i, point) in points.iter().enumerate() {
+        let mut numerator = E::ScalarField::ONE;
+        let mut denominator = E::ScalarField::ONE;
+        for (j, other) in points.iter().enumerate() {
+            if i != j {
+                numerator *= z - other;
+                denominator *= *point - other;
+            }
+        }
+        coefficients.push(numerator * denominator.inverse().ok_or(SolvencyError::DuplicatePoints)?);
+    }
+    Ok(coefficients)
+}